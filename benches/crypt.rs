@@ -43,7 +43,7 @@ pub fn crypt(c: &mut Criterion) {
 
     crypt.target = Target::Decrypt;
 
-    crypt.keys = Keys::from(crypt.keys.public().unwrap());
+    crypt.keys = crypt.keys.public().unwrap();
 
     c.bench_function("decrypt", |b| {
         b.iter(|| {
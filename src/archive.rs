@@ -0,0 +1,191 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    path::{Component, Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tar::{self, EntryType, HeaderMode};
+
+pub const CONTENT_TYPE: &str = "application/x-tar";
+
+/// A file or directory of an archive, its path relative to the archived
+/// directory.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    pub path: String,
+    pub directory: bool,
+    pub size: u64,
+    pub mode: u32,
+    pub modified: u64,
+}
+
+/// Packs a directory into a tar archive, keeping the relative paths,
+/// permissions and modification times. Symbolic links are rejected rather
+/// than followed out of the directory. The archive is built in memory, like
+/// any other crypt input, so the directory has to fit in it.
+pub fn pack(directory: &Path) -> Result<Vec<u8>, String> {
+    if !directory.is_dir() {
+        return Err(format!("{} is not a directory!", directory.display()));
+    }
+    unlinked(directory)?;
+    let mut builder = tar::Builder::new(vec![]);
+    builder.mode(HeaderMode::Complete);
+    // Links made after the check are stored as links, which unpacking rejects.
+    builder.follow_symlinks(false);
+    match builder
+        .append_dir_all(".", directory)
+        .and_then(|_| builder.into_inner())
+    {
+        Ok(archive) => Ok(archive),
+        Err(error) => Err(format!(
+            "Failed to pack the {} directory: {}!",
+            directory.display(),
+            error
+        )),
+    }
+}
+
+/// Lists the entries of an archive, rejecting the whole archive if any of
+/// them could land outside of the destination or isn't a file or directory.
+pub fn entries(archive: &[u8]) -> Result<Vec<Entry>, String> {
+    let corrupted = |error| format!("The archive is corrupted: {}!", error);
+    let mut entries = vec![];
+    for entry in tar::Archive::new(archive).entries().map_err(corrupted)? {
+        let entry = entry.map_err(corrupted)?;
+        let path = entry.path().map_err(corrupted)?.to_path_buf();
+        let header = entry.header();
+        let directory = match header.entry_type() {
+            EntryType::Regular => false,
+            EntryType::Directory => true,
+            kind => {
+                return Err(format!(
+                    "The archive entry {} is a {:?}, only files and directories are supported!",
+                    path.display(),
+                    kind
+                ))
+            }
+        };
+        if let Some(relative) = relative(&path)? {
+            entries.push(Entry {
+                path: relative.to_string_lossy().to_string(),
+                directory,
+                size: header.size().map_err(corrupted)?,
+                mode: header.mode().map_err(corrupted)? & 0o7777,
+                modified: header.mtime().map_err(corrupted)?,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Unpacks an archive in the destination once all its entries are checked,
+/// restoring permissions and modification times.
+pub fn unpack(archive: &[u8], destination: &Path, force: bool) -> Result<Vec<Entry>, String> {
+    let entries = entries(archive)?;
+    if !force {
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| !entry.directory && destination.join(&entry.path).exists())
+        {
+            return Err(format!(
+                "Refusing to overwrite the existing {} file (use --force)!",
+                destination.join(&entry.path).display()
+            ));
+        }
+    }
+    let failed = |error| format!("Failed to extract the archive: {}!", error);
+    fs::create_dir_all(destination).map_err(failed)?;
+
+    let mut unpacker = tar::Archive::new(archive);
+    unpacker.set_preserve_permissions(true);
+    unpacker.set_preserve_mtime(true);
+    for entry in unpacker.entries().map_err(failed)? {
+        let mut entry = entry.map_err(failed)?;
+        if relative(&entry.path().map_err(failed)?)?.is_none() {
+            continue;
+        }
+        if !entry.unpack_in(destination).map_err(failed)? {
+            return Err(format!(
+                "The archive entry {} escapes the destination!",
+                entry.path().map_err(failed)?.display()
+            ));
+        }
+    }
+    // tar leaves the modification times of directories alone, and writing their files changed them.
+    for entry in entries.iter().filter(|entry| entry.directory) {
+        File::open(destination.join(&entry.path))
+            .and_then(|directory| {
+                directory.set_modified(UNIX_EPOCH + Duration::from_secs(entry.modified))
+            })
+            .map_err(failed)?;
+    }
+    Ok(entries)
+}
+
+fn unlinked(directory: &Path) -> Result<(), String> {
+    let failed = |error| format!("Failed to pack the {} directory: {}!", directory.display(), error);
+    for entry in fs::read_dir(directory).map_err(failed)? {
+        let path = entry.map_err(failed)?.path();
+        let kind = fs::symlink_metadata(&path).map_err(failed)?.file_type();
+        if kind.is_symlink() {
+            return Err(format!(
+                "{} is a symbolic link, only files and directories are supported!",
+                path.display()
+            ));
+        }
+        if kind.is_dir() {
+            unlinked(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn relative(path: &Path) -> Result<Option<PathBuf>, String> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "The archive entry {} escapes the destination!",
+                    path.display()
+                ))
+            }
+        }
+    }
+    Ok((!relative.as_os_str().is_empty()).then_some(relative))
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}{:04o} {} bytes, modified at {}: {}{}",
+            if self.directory { "d" } else { "-" },
+            self.mode,
+            self.size,
+            self.modified,
+            self.path,
+            if self.directory { "/" } else { "" }
+        )
+    }
+}
@@ -0,0 +1,244 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::consts;
+
+use std::{fmt, str};
+
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+use blake3;
+use hex;
+
+pub const BOUNDARY: &str = "-----";
+
+pub const LINE_BYTES: usize = 48;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Message,
+    Key,
+    PublicKey,
+    Share,
+    Signature,
+    Revocation,
+    Certificate,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Message => write!(formatter, "MESSAGE"),
+            Self::Key => write!(formatter, "KEY"),
+            Self::PublicKey => write!(formatter, "PUBLIC KEY"),
+            Self::Share => write!(formatter, "SHARE"),
+            Self::Signature => write!(formatter, "SIGNATURE"),
+            Self::Revocation => write!(formatter, "REVOCATION"),
+            Self::Certificate => write!(formatter, "CERTIFICATE"),
+        }
+    }
+}
+
+impl str::FromStr for Kind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "MESSAGE" => Ok(Kind::Message),
+            "KEY" => Ok(Kind::Key),
+            "PUBLIC KEY" => Ok(Kind::PublicKey),
+            "SHARE" => Ok(Kind::Share),
+            "SIGNATURE" => Ok(Kind::Signature),
+            "REVOCATION" => Ok(Kind::Revocation),
+            "CERTIFICATE" => Ok(Kind::Certificate),
+            _ => Err(format!("Unknown armor kind '{}'!", kind)),
+        }
+    }
+}
+
+/// ASCII armor: `-----BEGIN KOMATTA <KIND>-----`, `Name: value` headers, a blank
+/// line, base64 body lines each followed by a line checksum, a `=` whole-body
+/// checksum and the matching `-----END KOMATTA <KIND>-----` marker.
+#[derive(Debug, Clone)]
+pub struct Armor {
+    pub kind: Kind,
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+impl Armor {
+    pub fn encode(&self) -> String {
+        let mut lines: Vec<String> = vec![Self::marker("BEGIN", self.kind)];
+        for (name, value) in &self.headers {
+            lines.push(format!("{}: {}", name, value));
+        }
+        lines.push(String::new());
+        for (offset, chunk) in self.data.chunks(LINE_BYTES).enumerate() {
+            lines.push(format!(
+                "{} {}",
+                base64.encode(chunk),
+                hex::encode(Self::lineChecksum(offset, chunk))
+            ));
+        }
+        lines.push(format!("={}", base64.encode(Self::checksum(&self.data))));
+        lines.push(Self::marker("END", self.kind));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(number, line)| (number + 1, line.trim()))
+            .skip_while(|(_, line)| line.is_empty());
+
+        let kind = match lines.next() {
+            Some((number, line)) => Self::kind(line, "BEGIN")
+                .ok_or(format!("Line {}: expected a BEGIN KOMATTA marker!", number))?,
+            None => return Err("The armor is empty!".to_string()),
+        };
+
+        let mut headers: Vec<(String, String)> = vec![];
+        let mut body: Vec<(usize, &str)> = vec![];
+        let mut inHeaders = true;
+        let mut checksum: Option<(usize, &str)> = None;
+        let mut ended = false;
+
+        for (number, line) in lines {
+            if let Some(endKind) = Self::kind(line, "END") {
+                if endKind != kind {
+                    return Err(format!(
+                        "Line {}: END marker for {} doesn't match BEGIN marker for {}!",
+                        number, endKind, kind
+                    ));
+                }
+                ended = true;
+                break;
+            }
+            if inHeaders {
+                if line.is_empty() {
+                    inHeaders = false;
+                    continue;
+                }
+                if let Some((name, value)) = line.split_once(": ") {
+                    headers.push((name.to_string(), value.to_string()));
+                    continue;
+                }
+                inHeaders = false;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(sum) = line.strip_prefix('=') {
+                checksum = Some((number, sum));
+            } else if let Some((checksumLine, _)) = checksum {
+                return Err(format!(
+                    "Line {}: unexpected data after the checksum on line {}!",
+                    number, checksumLine
+                ));
+            } else {
+                body.push((number, line));
+            }
+        }
+
+        if !ended {
+            return Err(format!("Missing the END KOMATTA {} marker!", kind));
+        }
+
+        let mut data: Vec<u8> = vec![];
+        for (offset, (number, line)) in body.into_iter().enumerate() {
+            let (encoded, lineChecksum) = line
+                .rsplit_once(' ')
+                .ok_or(format!("Line {} is corrupted: missing its checksum!", number))?;
+            let chunk = base64
+                .decode(encoded)
+                .map_err(|_| format!("Line {} is corrupted: invalid base64!", number))?;
+            if hex::encode(Self::lineChecksum(offset, &chunk)) != lineChecksum.to_lowercase() {
+                return Err(format!(
+                    "Line {} is corrupted: checksum mismatch!",
+                    number
+                ));
+            }
+            data.extend(chunk);
+        }
+
+        match checksum {
+            Some((number, sum)) => {
+                if base64.decode(sum).ok() != Some(Self::checksum(&data)) {
+                    return Err(format!(
+                        "Line {}: the armor checksum doesn't match (lines may be missing or reordered)!",
+                        number
+                    ));
+                }
+            }
+            None => return Err("Missing the armor checksum!".to_string()),
+        }
+
+        Ok(Self {
+            kind,
+            headers,
+            data,
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn isArmored(text: &[u8]) -> bool {
+        String::from_utf8_lossy(text)
+            .trim_start()
+            .starts_with(&format!("{}BEGIN KOMATTA ", BOUNDARY))
+    }
+
+    pub fn new(kind: Kind, data: Vec<u8>) -> Self {
+        Self {
+            kind,
+            headers: vec![(
+                "Version".to_string(),
+                format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            )],
+            data,
+        }
+    }
+
+    fn marker(action: &str, kind: Kind) -> String {
+        format!("{}{} KOMATTA {}{}", BOUNDARY, action, kind, BOUNDARY)
+    }
+
+    fn kind(line: &str, action: &str) -> Option<Kind> {
+        line.strip_prefix(BOUNDARY)?
+            .strip_suffix(BOUNDARY)?
+            .strip_prefix(action)?
+            .strip_prefix(" KOMATTA ")?
+            .parse()
+            .ok()
+    }
+
+    fn lineChecksum(offset: usize, chunk: &[u8]) -> Vec<u8> {
+        let mut Hasher = blake3::Hasher::new();
+        Hasher.update(&(offset as u32).to_be_bytes());
+        Hasher.update(chunk);
+        Hasher.finalize().as_bytes()[..consts::ARMOR_LINE_CHECKSUM_SIZE.default as usize].to_vec()
+    }
+
+    fn checksum(data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes()[..consts::ARMOR_CHECKSUM_SIZE.default as usize].to_vec()
+    }
+}
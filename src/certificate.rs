@@ -0,0 +1,289 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    armor::{self, Armor},
+    consts,
+    keys::Signing::{self, Usage},
+    ops::timestamp,
+    revocation::Revocation,
+};
+
+use flexbuffers;
+use hex;
+use serde::{Deserialize, Serialize};
+
+pub const DOMAIN: &str = "Komatta 2022 key certificate";
+
+/// Binds a signing public key, its key ID and a name to the key of the issuer
+/// that vouches for them, for a limited time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub version: u8,
+    pub subject: Signing::Keys<Option<Vec<u8>>>,
+    pub id: Vec<u8>,
+    pub name: String,
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub issuer: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn certify(
+        issuer: &Signing::Keys<Option<Vec<u8>>>,
+        subject: &Signing::Keys<Option<Vec<u8>>>,
+        name: String,
+        expires: Option<u64>,
+    ) -> Result<Self, String> {
+        let created = timestamp();
+        issuer.permits(Usage::Certify, created)?;
+        let subject = subject.public()?;
+        let mut certificate = Self {
+            version: consts::CERTIFICATE_VERSION,
+            id: subject.id()?,
+            subject,
+            name,
+            created,
+            expires,
+            issuer: issuer.id()?,
+            signature: vec![],
+        };
+        certificate.signature = issuer.signUnchecked(certificate.digest())?;
+        Ok(certificate)
+    }
+
+    pub fn verify(&self, issuer: &Signing::Keys<Option<Vec<u8>>>, at: u64) -> Result<bool, String> {
+        if self.version != consts::CERTIFICATE_VERSION {
+            return Err(format!("Unsupported certificate version {}!", self.version));
+        }
+        if self.issuer != issuer.id()? {
+            return Err(format!(
+                "The certificate was issued by {}, not by {}!",
+                hex::encode(&self.issuer),
+                hex::encode(issuer.id()?)
+            ));
+        }
+        if self.id != self.subject.id()? {
+            return Err("The certificate's key ID doesn't match its key!".to_string());
+        }
+        if at < self.created {
+            return Err(format!(
+                "The certificate for '{}' is not valid before {}!",
+                self.name, self.created
+            ));
+        }
+        if let Some(expires) = self.expires {
+            if at >= expires {
+                return Err(format!(
+                    "The certificate for '{}' expired at {}!",
+                    self.name, expires
+                ));
+            }
+        }
+        issuer.permits(Usage::Certify, at)?;
+        issuer
+            .public()?
+            .verifyUnchecked(self.digest(), self.signature.clone())
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        [
+            DOMAIN.as_bytes().to_vec(),
+            [self.version].to_vec(),
+            self.subject.public.clone().unwrap_or_default(),
+            [self.subject.algorithm.into()].to_vec(),
+            match &self.subject.validity {
+                Some(validity) => [
+                    [1].to_vec(),
+                    validity.created.to_be_bytes().to_vec(),
+                    match validity.expires {
+                        Some(expires) => [[1].to_vec(), expires.to_be_bytes().to_vec()].concat(),
+                        None => [0].to_vec(),
+                    },
+                    [validity
+                        .usage
+                        .iter()
+                        .fold(0, |flags, usage| flags | Into::<u8>::into(*usage))]
+                    .to_vec(),
+                ]
+                .concat(),
+                None => [0].to_vec(),
+            },
+            self.id.clone(),
+            (self.name.len() as u32).to_be_bytes().to_vec(),
+            self.name.as_bytes().to_vec(),
+            self.created.to_be_bytes().to_vec(),
+            match self.expires {
+                Some(expires) => [[1].to_vec(), expires.to_be_bytes().to_vec()].concat(),
+                None => [0].to_vec(),
+            },
+            self.issuer.clone(),
+        ]
+        .concat()
+    }
+
+    pub fn armor(&self) -> Result<String, String> {
+        let mut armor = Armor::new(
+            armor::Kind::Certificate,
+            match TryInto::<Vec<u8>>::try_into(self.clone()) {
+                Ok(encoded) => encoded,
+                Err(_) => return Err("Failed to serialize the certificate!".to_string()),
+            },
+        );
+        armor
+            .headers
+            .push(("Key-ID".to_string(), hex::encode(&self.id)));
+        armor.headers.push(("Name".to_string(), self.name.clone()));
+        armor
+            .headers
+            .push(("Issuer".to_string(), hex::encode(&self.issuer)));
+        Ok(armor.encode())
+    }
+
+    pub fn dearmor(text: &str) -> Result<Self, String> {
+        let armor = Armor::decode(text)?;
+        if armor.kind != armor::Kind::Certificate {
+            return Err(format!(
+                "Expected a certificate but found a {}!",
+                armor.kind
+            ));
+        }
+        match Self::try_from(armor.data) {
+            Ok(certificate) => Ok(certificate),
+            Err(_) => Err("The certificate is corrupted!".to_string()),
+        }
+    }
+}
+
+/// Trusted root keys and the certificates that may link other keys to them.
+#[derive(Debug, Clone, Default)]
+pub struct Trust {
+    pub roots: Vec<Signing::Keys<Option<Vec<u8>>>>,
+    pub certificates: Vec<Certificate>,
+}
+
+impl Trust {
+    /// Finds the key with this ID through a chain of certificates to one of
+    /// the roots, none of its keys revoked at `at`.
+    pub fn resolve(
+        &self,
+        id: &Vec<u8>,
+        at: u64,
+        revocations: &[Revocation],
+    ) -> Result<Signing::Keys<Option<Vec<u8>>>, String> {
+        self.walk(id, at, revocations, consts::CHAIN_DEPTH.default)
+    }
+
+    fn walk(
+        &self,
+        id: &Vec<u8>,
+        at: u64,
+        revocations: &[Revocation],
+        depth: u16,
+    ) -> Result<Signing::Keys<Option<Vec<u8>>>, String> {
+        if let Some(root) = self
+            .roots
+            .iter()
+            .find(|root| root.id().ok().as_ref() == Some(id))
+        {
+            unrevoked(root, at, revocations)?;
+            return root.public();
+        }
+        if depth == 0 {
+            return Err(format!(
+                "The chain of {} is longer than {} certificates!",
+                hex::encode(id),
+                consts::CHAIN_DEPTH.default
+            ));
+        }
+        let mut failures = vec![];
+        for certificate in self
+            .certificates
+            .iter()
+            .filter(|certificate| &certificate.id == id)
+        {
+            let verified = self
+                .walk(&certificate.issuer, at, revocations, depth - 1)
+                .and_then(|issuer| certificate.verify(&issuer, at))
+                .and_then(|verified| {
+                    unrevoked(&certificate.subject, at, revocations).map(|_| verified)
+                });
+            match verified {
+                Ok(true) => return certificate.subject.public(),
+                Ok(false) => failures.push(format!(
+                    "the certificate for '{}' is invalid",
+                    certificate.name
+                )),
+                Err(error) => failures.push(error),
+            }
+        }
+        if failures.is_empty() {
+            Err(format!(
+                "The key {} isn't certified by a trusted root!",
+                hex::encode(id)
+            ))
+        } else {
+            Err(format!(
+                "The key {} isn't certified by a trusted root ({})!",
+                hex::encode(id),
+                failures.join(", ")
+            ))
+        }
+    }
+
+    pub fn new(roots: Vec<Signing::Keys<Option<Vec<u8>>>>, certificates: Vec<Certificate>) -> Self {
+        Self {
+            roots,
+            certificates,
+        }
+    }
+}
+
+fn unrevoked(
+    keys: &Signing::Keys<Option<Vec<u8>>>,
+    at: u64,
+    revocations: &[Revocation],
+) -> Result<(), String> {
+    let id = keys.id()?;
+    match revocations.iter().find(|revocation| {
+        revocation.key == id && revocation.revoked <= at && revocation.verify(keys).unwrap_or(false)
+    }) {
+        Some(revocation) => Err(format!(
+            "The key {} was revoked at {} ({})!",
+            hex::encode(&id),
+            revocation.revoked,
+            revocation.reason
+        )),
+        None => Ok(()),
+    }
+}
+
+impl TryInto<Vec<u8>> for Certificate {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Certificate {
+    type Error = flexbuffers::DeserializationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        flexbuffers::from_slice(&value)
+    }
+}
@@ -0,0 +1,130 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::consts;
+
+use std::{fmt, io::Cursor, str};
+
+use brotli;
+use serde::{Deserialize, Serialize};
+use zstd;
+
+pub const ORACLE_WARNING: &str = "Compressing secrets together with attacker-controlled data leaks information through the ciphertext length (compression oracle)!";
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Brotli,
+    Zstd,
+}
+
+impl Compression {
+    pub fn compress(&self, input: &Vec<u8>, level: Option<u16>) -> Result<Vec<u8>, String> {
+        match self {
+            Compression::None => Ok(input.clone()),
+            Compression::Brotli => {
+                let level = level.unwrap_or(consts::BROTLI_LEVEL.default);
+                consts::BROTLI_LEVEL.check(level)?;
+                let mut compressed = vec![];
+                if brotli::BrotliCompress(
+                    &mut Cursor::new(input),
+                    &mut compressed,
+                    &brotli::enc::BrotliEncoderParams {
+                        quality: level.into(),
+                        ..Default::default()
+                    },
+                )
+                .is_err()
+                {
+                    return Err("Failed to compress!".to_string());
+                }
+                Ok(compressed)
+            }
+            Compression::Zstd => {
+                let level = level.unwrap_or(consts::ZSTD_LEVEL.default);
+                consts::ZSTD_LEVEL.check(level)?;
+                match zstd::encode_all(Cursor::new(input), level.into()) {
+                    Ok(compressed) => Ok(compressed),
+                    Err(_) => Err("Failed to compress!".to_string()),
+                }
+            }
+        }
+    }
+
+    pub fn decompress(&self, input: &Vec<u8>) -> Result<Vec<u8>, String> {
+        match self {
+            Compression::None => Ok(input.clone()),
+            Compression::Brotli => {
+                let mut decompressed = vec![];
+                if brotli::BrotliDecompress(&mut Cursor::new(input), &mut decompressed).is_err() {
+                    return Err("Failed to decompress!".to_string());
+                }
+                Ok(decompressed)
+            }
+            Compression::Zstd => match zstd::decode_all(Cursor::new(input)) {
+                Ok(decompressed) => Ok(decompressed),
+                Err(_) => Err("Failed to decompress!".to_string()),
+            },
+        }
+    }
+
+    pub fn warning(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            _ => Some(ORACLE_WARNING),
+        }
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => 0,
+            Compression::Brotli => 1,
+            Compression::Zstd => 2,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::None => write!(formatter, "none"),
+            Self::Brotli => write!(formatter, "brotli"),
+            Self::Zstd => write!(formatter, "zstd"),
+        }
+    }
+}
+
+impl str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(compression: &str) -> Result<Self, Self::Err> {
+        match compression.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "brotli" => Ok(Compression::Brotli),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "Unknown compression '{}', the options are '{}', '{}' & '{}'!",
+                compression,
+                Compression::None,
+                Compression::Brotli,
+                Compression::Zstd
+            )),
+        }
+    }
+}
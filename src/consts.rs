@@ -14,13 +14,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::keys::Signing;
-
 use std::{fmt, fmt::Display, ops::Range};
 
 use argon2;
 use blake3;
-use pqcrypto::sign::dilithium5;
+
+pub const FORMAT_VERSION: u8 = 1;
+pub const SIGNATURE_VERSION: u8 = 1;
+pub const REVOCATION_VERSION: u8 = 1;
+pub const CERTIFICATE_VERSION: u8 = 1;
 
 pub struct Size<T> {
     pub name: &'static str,
@@ -35,7 +37,7 @@ pub enum Behaviour {
 }
 
 impl Size<u16> {
-    const fn new(name: &'static str, default: u16, behaviour: Behaviour) -> Self {
+    pub(crate) const fn new(name: &'static str, default: u16, behaviour: Behaviour) -> Self {
         Self {
             name,
             default,
@@ -77,12 +79,6 @@ impl Display for Size<u16> {
 
 pub const IV_SIZE: Size<u16> = Size::new("IV Size", 8, Behaviour::Ranged(8..u16::MAX));
 pub const BLOCK_SIZE: Size<u16> = Size::new("Block Size", 64, Behaviour::Ranged(4..u16::MAX));
-pub const SIGNATURE_SIZE: Size<u16> = Size::new(
-    "Signature Size",
-    dilithium5::signature_bytes() as u16,
-    Behaviour::Fixed,
-);
-
 pub const KEYED_HASH_SIZE: Size<u16> = Size::new(
     "Keyed Hash Size",
     32,
@@ -95,21 +91,23 @@ pub const CRYPT_KEY_SIZE: Size<u16> = Size::new(
     Behaviour::Ranged(16..u16::MAX),
 );
 pub const CRYPT_KEY_SIZE_VALUE: u16 = 16;
-pub const SIGNING_KEY_SIZE: Signing::Keys<Size<u16>> = Signing::Keys {
-    secret: Size::new(
-        "Signing Secret Key Size",
-        dilithium5::secret_key_bytes() as u16,
-        Behaviour::Fixed,
-    ),
-    public: Size::new(
-        "Signing Public Key Size",
-        dilithium5::public_key_bytes() as u16,
-        Behaviour::Fixed,
-    ),
-};
 
 pub const SALT_SIZE: Size<u16> = Size::new(
     "Salt Size",
     8,
     Behaviour::Ranged(argon2::MIN_SALT_LEN as u16..u16::MAX),
 );
+
+pub const KEY_ID_SIZE: Size<u16> = Size::new("Key ID Size", 8, Behaviour::Fixed);
+
+pub const ARMOR_CHECKSUM_SIZE: Size<u16> = Size::new("Armor Checksum Size", 3, Behaviour::Fixed);
+pub const ARMOR_LINE_CHECKSUM_SIZE: Size<u16> =
+    Size::new("Armor Line Checksum Size", 2, Behaviour::Fixed);
+
+pub const SHARES: Size<u16> = Size::new("Shares", 3, Behaviour::Ranged(1..256));
+pub const SHARE_CHECKSUM_SIZE: Size<u16> = Size::new("Share Checksum Size", 4, Behaviour::Fixed);
+
+pub const BROTLI_LEVEL: Size<u16> = Size::new("Brotli Level", 9, Behaviour::Ranged(0..12));
+pub const ZSTD_LEVEL: Size<u16> = Size::new("Zstd Level", 3, Behaviour::Ranged(1..23));
+
+pub const CHAIN_DEPTH: Size<u16> = Size::new("Chain Depth", 8, Behaviour::Fixed);
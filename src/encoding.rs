@@ -0,0 +1,261 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::armor::{self, Armor};
+
+use std::{fmt, io::Cursor, str};
+
+use base64::{
+    engine::general_purpose::{STANDARD as base64, URL_SAFE_NO_PAD as base64Url},
+    Engine,
+};
+use brotli;
+use bs58;
+use data_encoding::{self, Specification};
+use hex;
+use z85;
+
+pub const CROCKFORD_SYMBOLS: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Encoding {
+    #[default]
+    Base64,
+    Base64Url,
+    Hex,
+    Base32,
+    Base58,
+    Z85,
+}
+
+impl Encoding {
+    /// The encodings tried when auto-detecting, the ones with the smallest
+    /// alphabets first since their text is usually valid in the larger ones.
+    pub const DETECTION: [Encoding; 6] = [
+        Encoding::Hex,
+        Encoding::Base32,
+        Encoding::Base58,
+        Encoding::Base64Url,
+        Encoding::Base64,
+        Encoding::Z85,
+    ];
+
+    pub fn encode(&self, value: &[u8]) -> String {
+        match self {
+            Self::Base64 => base64.encode(value),
+            Self::Base64Url => base64Url.encode(value),
+            Self::Hex => hex::encode(value),
+            Self::Base32 => crockford().encode(value),
+            Self::Base58 => bs58::encode(value).into_string(),
+            Self::Z85 => z85::encode(value),
+        }
+    }
+
+    pub fn decode(&self, value: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Base64 => base64.decode(value).ok(),
+            Self::Base64Url => base64Url.decode(value).ok(),
+            Self::Hex => hex::decode(value).ok(),
+            Self::Base32 => crockford().decode(value).ok(),
+            Self::Base58 => bs58::decode(value).into_vec().ok(),
+            Self::Z85 => z85::decode(value).ok(),
+        }
+        .ok_or(format!("Not valid {}!", self.name()))
+    }
+
+    /// The encodings whose alphabet covers the value, in detection order.
+    pub fn detect(value: &[u8]) -> Vec<Encoding> {
+        Self::DETECTION
+            .into_iter()
+            .filter(|encoding| value.iter().all(|byte| encoding.accepts(*byte)))
+            .collect()
+    }
+
+    fn accepts(&self, byte: u8) -> bool {
+        match self {
+            Self::Base64 => byte.is_ascii_alphanumeric() || b"+/=".contains(&byte),
+            Self::Base64Url => byte.is_ascii_alphanumeric() || b"-_".contains(&byte),
+            Self::Hex => byte.is_ascii_hexdigit(),
+            Self::Base32 => (byte.is_ascii_alphanumeric() && !b"Uu".contains(&byte)) || byte == b'-',
+            Self::Base58 => byte.is_ascii_alphanumeric() && !b"0OIl".contains(&byte),
+            Self::Z85 => byte.is_ascii_alphanumeric() || b".-:+=^!/*?&<>()[]{}@%$#".contains(&byte),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Base64 => "Base64",
+            Self::Base64Url => "URL-safe Base64",
+            Self::Hex => "Base16",
+            Self::Base32 => "Crockford Base32",
+            Self::Base58 => "Base58",
+            Self::Z85 => "Z85",
+        }
+    }
+}
+
+/// Crockford's Base32: no padding, case-insensitive, `I` and `L` read as `1`,
+/// `O` as `0` and hyphens ignored, so that it survives being read aloud.
+fn crockford() -> data_encoding::Encoding {
+    let mut specification = Specification::new();
+    specification.symbols.push_str(CROCKFORD_SYMBOLS);
+    specification
+        .translate
+        .from
+        .push_str("abcdefghjkmnpqrstvwxyzIiLlOo");
+    specification
+        .translate
+        .to
+        .push_str("ABCDEFGHJKMNPQRSTVWXYZ111100");
+    specification.ignore.push('-');
+    specification.encoding().unwrap()
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Base64 => write!(formatter, "base64"),
+            Self::Base64Url => write!(formatter, "base64url"),
+            Self::Hex => write!(formatter, "hex"),
+            Self::Base32 => write!(formatter, "base32"),
+            Self::Base58 => write!(formatter, "base58"),
+            Self::Z85 => write!(formatter, "z85"),
+        }
+    }
+}
+
+impl str::FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(encoding: &str) -> Result<Self, Self::Err> {
+        match encoding {
+            "base64" => Ok(Self::Base64),
+            "base64url" => Ok(Self::Base64Url),
+            "hex" => Ok(Self::Hex),
+            "base32" => Ok(Self::Base32),
+            "base58" => Ok(Self::Base58),
+            "z85" => Ok(Self::Z85),
+            _ => Err(format!(
+                "'{}' is not an encoding (base64, base64url, hex, base32, base58 or z85)!",
+                encoding
+            )),
+        }
+    }
+}
+
+/// Turns crypts and keys into text (optionally compressed with Brotli, then
+/// encoded or ASCII-armored) and back.
+pub struct Transform {
+    value: Vec<u8>,
+    encoding: Encoding,
+    compress: bool,
+    armor: Option<Armor>,
+}
+
+impl Transform {
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut value = vec![];
+        if self.compress {
+            if brotli::BrotliCompress(
+                &mut Cursor::new(self.value.clone()),
+                &mut value,
+                &brotli::enc::BrotliEncoderParams::default(),
+            )
+            .is_err()
+            {
+                return Err("Failed to compress!".to_string());
+            }
+        } else {
+            value = self.value.clone()
+        }
+        Ok({
+            if let Some(armor) = &self.armor {
+                Armor {
+                    data: value,
+                    ..armor.clone()
+                }
+                .encode()
+                .as_bytes()
+                .to_vec()
+            } else {
+                self.encoding.encode(&value).as_bytes().to_vec()
+            }
+        })
+    }
+
+    /// Decodes with the chosen encoding, falling back to the detected ones
+    /// whose result decompresses (when compressed).
+    pub fn decode(&self) -> Result<Vec<u8>, String> {
+        if Armor::isArmored(&self.value) {
+            let armor = Armor::decode(&String::from_utf8_lossy(&self.value))?;
+            return self.decompress(armor.data);
+        }
+        let error = match self
+            .encoding
+            .decode(&self.value)
+            .and_then(|decoded| self.decompress(decoded))
+        {
+            Ok(decompressed) => return Ok(decompressed),
+            Err(error) => error,
+        };
+        Encoding::detect(&self.value)
+            .into_iter()
+            .filter(|encoding| *encoding != self.encoding)
+            .find_map(|encoding| {
+                encoding
+                    .decode(&self.value)
+                    .and_then(|decoded| self.decompress(decoded))
+                    .ok()
+            })
+            .ok_or(error)
+    }
+
+    fn decompress(&self, decoded: Vec<u8>) -> Result<Vec<u8>, String> {
+        if self.compress {
+            let (mut compressed, mut decompressed) = (Cursor::new(decoded), vec![]);
+            if brotli::BrotliDecompress(&mut compressed, &mut decompressed).is_err() {
+                return Err("Failed to decompress!".to_string());
+            }
+            Ok(decompressed)
+        } else {
+            Ok(decoded)
+        }
+    }
+
+    pub fn armored(value: Vec<u8>, compress: bool, kind: armor::Kind, id: Option<Vec<u8>>) -> Self {
+        let mut armor = Armor::new(kind, vec![]);
+        if let Some(id) = id {
+            armor
+                .headers
+                .push(("Key-ID".to_string(), hex::encode(id)));
+        }
+        Self {
+            value,
+            encoding: Encoding::Base64,
+            compress,
+            armor: Some(armor),
+        }
+    }
+
+    pub fn new(value: Vec<u8>, encoding: Encoding, compress: bool) -> Self {
+        Self {
+            value,
+            encoding,
+            compress,
+            armor: None,
+        }
+    }
+}
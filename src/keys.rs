@@ -14,31 +14,52 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{consts, ops::randomness};
+use crate::{
+    consts,
+    ops::{keyedHash, randomness},
+    shamir::{self, Share},
+};
+
+use std::{fmt, str};
 
 use argon2;
 use flexbuffers;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Keys {
     salt: Vec<u8>,
     cypher: Vec<u8>,
     signing: Option<Signing::Keys<Option<Vec<u8>>>>,
+    #[serde(default)]
+    cost: Cost,
     #[serde(skip_serializing, skip_deserializing)]
     subKey: Option<Vec<u8>>,
 }
 
+/// The Argon2 costs of the sub key derivation, kept along the keys so that
+/// both ends derive the same sub key.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Cost {
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
 impl Keys {
     fn constraint(&self) -> Result<&Self, String> {
         consts::CRYPT_KEY_SIZE.check(self.cypher.len() as u16)?;
-        if let Some(signing) = self.signing.clone() {
-            if let Some(secret) = signing.secret {
-                consts::SIGNING_KEY_SIZE.secret.check(secret.len() as u16)?;
-            }
-            if let Some(public) = signing.public {
-                consts::SIGNING_KEY_SIZE.public.check(public.len() as u16)?;
-            }
+        consts::SALT_SIZE.check(self.salt.len() as u16)?;
+        if let Err(error) = argon2::Params::new(
+            self.cost.memory,
+            self.cost.iterations,
+            self.cost.parallelism,
+            None,
+        ) {
+            return Err(format!("Invalid Argon2 parameters: {}!", error));
+        }
+        if let Some(signing) = &self.signing {
+            signing.constraint()?;
         }
         Ok(self)
     }
@@ -59,15 +80,24 @@ impl Keys {
             let Deriver =
                 argon2::Argon2::new(argon2::Algorithm::default(), argon2::Version::default(), {
                     let mut Params = argon2::ParamsBuilder::new();
-                    Params.output_len(self.cypher.len());
-                    Params.build().unwrap()
+                    Params
+                        .m_cost(self.cost.memory)
+                        .t_cost(self.cost.iterations)
+                        .p_cost(self.cost.parallelism)
+                        .output_len(self.cypher.len());
+                    match Params.build() {
+                        Ok(params) => params,
+                        Err(error) => {
+                            return Err(format!("Invalid Argon2 parameters: {}!", error))
+                        }
+                    }
                 });
 
             let mut Derived: Vec<u8> = vec![0; self.cypher.len()];
 
-            Deriver
-                .hash_password_into(&self.cypher.clone(), &self.salt.clone(), &mut Derived)
-                .unwrap();
+            if let Err(error) = Deriver.hash_password_into(&self.cypher, &self.salt, &mut Derived) {
+                return Err(format!("Failed to derive the sub key: {}!", error));
+            }
 
             self.subKey = Some(Derived.clone());
 
@@ -75,12 +105,21 @@ impl Keys {
         }
     }
 
+    pub fn id(&mut self) -> Result<Vec<u8>, String> {
+        Ok(keyedHash(
+            b"Komatta key ID",
+            &self.subKey()?,
+            Some(consts::KEY_ID_SIZE.default),
+        ))
+    }
+
     pub fn public(&self) -> Result<Self, String> {
         if let Some(signing) = self.signing.clone() {
             Ok(Self {
                 salt: self.salt.clone(),
                 cypher: self.cypher.clone(),
                 signing: Some(signing.public()?),
+                cost: self.cost,
                 subKey: None,
             })
         } else {
@@ -88,10 +127,49 @@ impl Keys {
         }
     }
 
+    pub fn split(&self, threshold: u8, shares: u8) -> Result<Vec<Share>, String> {
+        let secret = match TryInto::<Vec<u8>>::try_into(self.clone()) {
+            Ok(secret) => secret,
+            Err(_) => return Err("Failed to serialize the keys!".to_string()),
+        };
+        shamir::split(&secret, self.clone().id()?, threshold, shares)
+    }
+
+    pub fn combine(shares: &Vec<Share>) -> Result<Self, String> {
+        let mut keys = match Self::try_from(shamir::combine(shares)?) {
+            Ok(keys) => keys,
+            Err(_) => return Err("The combined shares aren't valid keys!".to_string()),
+        };
+        if let Some(share) = shares.first() {
+            if keys.id()? != share.id {
+                return Err("The combined keys don't match the shares' key ID!".to_string());
+            }
+        }
+        Ok(keys)
+    }
+
     pub fn new(
         saltSize: Option<u16>,
         cryptKeySize: Option<u16>,
         signing: bool,
+    ) -> Result<Self, String> {
+        Self::generate(
+            saltSize,
+            cryptKeySize,
+            signing.then(Signing::Algorithm::default),
+            None,
+            vec![Signing::Usage::Sign],
+            Cost::default(),
+        )
+    }
+
+    pub fn generate(
+        saltSize: Option<u16>,
+        cryptKeySize: Option<u16>,
+        signing: Option<Signing::Algorithm>,
+        expires: Option<u64>,
+        usage: Vec<Signing::Usage>,
+        cost: Cost,
     ) -> Result<Self, String> {
         let keys = Self {
             salt: randomness({
@@ -108,74 +186,306 @@ impl Keys {
                     consts::CRYPT_KEY_SIZE.default
                 }
             }),
-            signing: {
-                if signing {
-                    Some(Signing::Keys::new())
-                } else {
-                    None
-                }
-            },
+            signing: signing
+                .map(|algorithm| Signing::Keys::generate(algorithm, expires, usage)),
+            cost,
             subKey: None,
         };
         keys.constraint()?;
         Ok(keys)
     }
+
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    pub fn saltSize(&self) -> usize {
+        self.salt.len()
+    }
+
+    pub fn keySize(&self) -> usize {
+        self.cypher.len()
+    }
 }
 
 pub mod Signing {
+    use crate::{
+        consts::{self, Behaviour, Size},
+        ops::timestamp,
+    };
+
+    use blake3;
     use pqcrypto::{prelude::*, sign::dilithium5};
+    use pqcrypto_mldsa::{mldsa44, mldsa65, mldsa87};
+
+    pub const DOMAIN: &str = "Komatta 2022 signing key self-signature";
+
+    /// Runs `$body` with `$scheme` bound to the pqcrypto module of the algorithm.
+    macro_rules! dispatch {
+        ($algorithm:expr, $scheme:ident => $body:expr) => {
+            match $algorithm {
+                Algorithm::Dilithium5 => {
+                    use dilithium5 as $scheme;
+                    $body
+                }
+                Algorithm::MlDsa44 => {
+                    use mldsa44 as $scheme;
+                    $body
+                }
+                Algorithm::MlDsa65 => {
+                    use mldsa65 as $scheme;
+                    $body
+                }
+                Algorithm::MlDsa87 => {
+                    use mldsa87 as $scheme;
+                    $body
+                }
+            }
+        };
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy, super::Serialize, super::Deserialize)]
+    pub enum Algorithm {
+        Dilithium5,
+        MlDsa44,
+        MlDsa65,
+        MlDsa87,
+    }
+
+    impl Algorithm {
+        pub fn legacy() -> Self {
+            Self::Dilithium5
+        }
+
+        pub fn size(&self) -> Keys<Size<u16>> {
+            dispatch!(self, scheme => Keys {
+                secret: Size::new(
+                    "Signing Secret Key Size",
+                    scheme::secret_key_bytes() as u16,
+                    Behaviour::Fixed,
+                ),
+                public: Size::new(
+                    "Signing Public Key Size",
+                    scheme::public_key_bytes() as u16,
+                    Behaviour::Fixed,
+                ),
+                algorithm: *self,
+                validity: None,
+            })
+        }
+
+        pub fn signatureSize(&self) -> Size<u16> {
+            dispatch!(self, scheme => Size::new(
+                "Signature Size",
+                scheme::signature_bytes() as u16,
+                Behaviour::Fixed,
+            ))
+        }
+
+        fn keypair(&self) -> (Vec<u8>, Vec<u8>) {
+            dispatch!(self, scheme => {
+                let keypair = scheme::keypair();
+                (keypair.0.as_bytes().to_vec(), keypair.1.as_bytes().to_vec())
+            })
+        }
+
+        fn sign(&self, input: &[u8], secret: &[u8]) -> Result<Vec<u8>, String> {
+            dispatch!(self, scheme => {
+                if let Ok(secret) = scheme::SecretKey::from_bytes(secret) {
+                    Ok(scheme::detached_sign(input, &secret).as_bytes().to_vec())
+                } else {
+                    Err("Error while trying to reconstruct the secret key!".to_string())
+                }
+            })
+        }
+
+        fn verify(&self, input: &[u8], signature: &[u8], public: &[u8]) -> Result<bool, String> {
+            dispatch!(self, scheme => {
+                if let Ok(public) = scheme::PublicKey::from_bytes(public) {
+                    if let Ok(signature) = scheme::DetachedSignature::from_bytes(signature) {
+                        Ok(scheme::verify_detached_signature(&signature, input, &public).is_ok())
+                    } else {
+                        Err("Error while trying to reconstruct the signature!".to_string())
+                    }
+                } else {
+                    Err("Error while trying to reconstruct the public key!".to_string())
+                }
+            })
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy, super::Serialize, super::Deserialize)]
+    pub enum Usage {
+        Sign,
+        Certify,
+    }
+
+    #[derive(Debug, Clone, super::Serialize, super::Deserialize)]
+    pub struct Validity {
+        pub created: u64,
+        pub expires: Option<u64>,
+        pub usage: Vec<Usage>,
+        signature: Vec<u8>,
+    }
 
     #[derive(Debug, Clone, super::Serialize, super::Deserialize)]
     pub struct Keys<T> {
         pub secret: T,
         pub public: T,
+        #[serde(default = "Algorithm::legacy")]
+        pub algorithm: Algorithm,
+        #[serde(default)]
+        pub validity: Option<Validity>,
     }
 
     impl Keys<Option<Vec<u8>>> {
+        #[allow(clippy::new_without_default)]
         pub fn new() -> Self {
-            let keypair = dilithium5::keypair();
-            Self {
-                secret: Some(keypair.1.as_bytes().to_vec()),
-                public: Some(keypair.0.as_bytes().to_vec()),
+            Self::generate(Algorithm::default(), None, vec![Usage::Sign])
+        }
+
+        pub(crate) fn constraint(&self) -> Result<&Self, String> {
+            let size = self.algorithm.size();
+            if let Some(secret) = &self.secret {
+                size.secret.check(secret.len() as u16)?;
+            }
+            if let Some(public) = &self.public {
+                size.public.check(public.len() as u16)?;
             }
+            Ok(self)
         }
 
-        pub fn sign(&self, input: Vec<u8>) -> Result<Vec<u8>, String> {
-            if let Some(secretBytes) = self.secret.clone() {
-                if let Ok(secret) = dilithium5::SecretKey::from_bytes(&secretBytes) {
-                    Ok(dilithium5::detached_sign(&input, &secret)
-                        .as_bytes()
-                        .to_vec())
-                } else {
-                    Err("Error while trying to reconstruct the secret key!".to_string())
+        /// Reads a public key exported on its own, which can only verify.
+        pub fn verifying(value: Vec<u8>) -> Result<Self, String> {
+            let keys: Self = match flexbuffers::from_slice(&value) {
+                Ok(keys) => keys,
+                Err(_) => return Err("The signing public key is malformed!".to_string()),
+            };
+            if keys.secret.is_some() || keys.public.is_none() {
+                return Err("The signing public key is malformed!".to_string());
+            }
+            keys.constraint()?;
+            Ok(keys)
+        }
+
+        pub fn generate(algorithm: Algorithm, expires: Option<u64>, usage: Vec<Usage>) -> Self {
+            let (public, secret) = algorithm.keypair();
+            let mut keys = Self {
+                secret: Some(secret.clone()),
+                public: Some(public),
+                algorithm,
+                validity: None,
+            };
+            let mut validity = Validity {
+                created: timestamp(),
+                expires,
+                usage,
+                signature: vec![],
+            };
+            validity.signature = algorithm
+                .sign(&keys.selfSigned(&validity), &secret)
+                .unwrap_or_default();
+            keys.validity = Some(validity);
+            keys
+        }
+
+        fn selfSigned(&self, validity: &Validity) -> Vec<u8> {
+            [
+                DOMAIN.as_bytes().to_vec(),
+                self.public.clone().unwrap_or_default(),
+                validity.created.to_be_bytes().to_vec(),
+                match validity.expires {
+                    Some(expires) => [[1].to_vec(), expires.to_be_bytes().to_vec()].concat(),
+                    None => [0].to_vec(),
+                },
+                [validity
+                    .usage
+                    .iter()
+                    .fold(0, |flags, usage| flags | Into::<u8>::into(*usage))]
+                .to_vec(),
+                match self.algorithm {
+                    Algorithm::Dilithium5 => vec![],
+                    algorithm => [algorithm.into()].to_vec(),
+                },
+            ]
+            .concat()
+        }
+
+        pub fn permits(&self, usage: Usage, at: u64) -> Result<(), String> {
+            // Keys made before validities existed may still sign, but never certify.
+            let validity = match &self.validity {
+                Some(validity) => validity,
+                None if usage == Usage::Sign => return Ok(()),
+                None => {
+                    return Err(format!(
+                        "The signing key has no validity, so it may not be used to {}!",
+                        usage
+                    ))
                 }
+            };
+            let public = match self.public.clone() {
+                Some(public) => public,
+                None => return Err("Unable to verify without the public key!".to_string()),
+            };
+            if !self
+                .algorithm
+                .verify(&self.selfSigned(validity), &validity.signature, &public)
+                .unwrap_or(false)
+            {
+                return Err("The signing key's self-signature is invalid!".to_string());
+            }
+            if at < validity.created {
+                return Err(format!(
+                    "The signing key is not valid before {}!",
+                    validity.created
+                ));
+            }
+            if let Some(expires) = validity.expires {
+                if at >= expires {
+                    return Err(format!("The signing key expired at {}!", expires));
+                }
+            }
+            if !validity.usage.contains(&usage) {
+                return Err(format!("The signing key may not be used to {}!", usage));
+            }
+            Ok(())
+        }
+
+        pub fn sign(&self, input: Vec<u8>) -> Result<Vec<u8>, String> {
+            self.permits(Usage::Sign, timestamp())?;
+            self.signUnchecked(input)
+        }
+
+        pub(crate) fn signUnchecked(&self, input: Vec<u8>) -> Result<Vec<u8>, String> {
+            if let Some(secret) = &self.secret {
+                self.algorithm.sign(&input, secret)
             } else {
                 Err("Unable to sign a message without the secret key!".to_string())
             }
         }
 
         pub fn verify(&self, input: Vec<u8>, signature: Vec<u8>) -> Result<bool, String> {
-            if let None = self.secret.clone() {
-                if let Some(publicBytes) = self.public.clone() {
-                    if let Ok(public) = dilithium5::PublicKey::from_bytes(&publicBytes) {
-                        if let Ok(detachedSignature) =
-                            dilithium5::DetachedSignature::from_bytes(&signature)
-                        {
-                            if let Ok(_) = dilithium5::verify_detached_signature(
-                                &detachedSignature,
-                                &input,
-                                &public,
-                            ) {
-                                Ok(true)
-                            } else {
-                                Err("Signature is invalid!".to_string())
-                            }
-                        } else {
-                            Err("Error while trying to reconstruct the signature!".to_string())
-                        }
-                    } else {
-                        Err("Error while trying to reconstruct the public key!".to_string())
-                    }
+            self.verifyAt(input, signature, timestamp())
+        }
+
+        pub fn verifyAt(
+            &self,
+            input: Vec<u8>,
+            signature: Vec<u8>,
+            at: u64,
+        ) -> Result<bool, String> {
+            self.permits(Usage::Sign, at)?;
+            self.verifyUnchecked(input, signature)
+        }
+
+        pub(crate) fn verifyUnchecked(
+            &self,
+            input: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> Result<bool, String> {
+            if self.secret.is_none() {
+                if let Some(public) = &self.public {
+                    self.algorithm.verify(&input, &signature, public)
                 } else {
                     Err("Unable to verify without the public key!".to_string())
                 }
@@ -184,8 +494,23 @@ pub mod Signing {
             }
         }
 
+        pub fn scheme(&self) -> String {
+            self.algorithm.to_string()
+        }
+
+        pub fn id(&self) -> Result<Vec<u8>, String> {
+            if let Some(public) = &self.public {
+                Ok(
+                    blake3::hash(public).as_bytes()[..consts::KEY_ID_SIZE.default as usize]
+                        .to_vec(),
+                )
+            } else {
+                Err("Cannot identify the signing keys without the public key!".to_string())
+            }
+        }
+
         pub fn public(&self) -> Result<Self, String> {
-            if let Some(_) = self.public {
+            if self.public.is_some() {
                 let mut signing = self.clone();
                 signing.secret = None;
                 Ok(signing)
@@ -196,13 +521,277 @@ pub mod Signing {
     }
 }
 
-impl Default for Keys {
+pub mod Kem {
+    use crate::consts;
+
+    use blake3;
+    use hex;
+    use pqcrypto::prelude::*;
+    use pqcrypto_mlkem::{mlkem1024, mlkem512, mlkem768};
+
+    /// Runs `$body` with `$scheme` bound to the pqcrypto module of the algorithm.
+    macro_rules! dispatch {
+        ($algorithm:expr, $scheme:ident => $body:expr) => {
+            match $algorithm {
+                Algorithm::MlKem512 => {
+                    use mlkem512 as $scheme;
+                    $body
+                }
+                Algorithm::MlKem768 => {
+                    use mlkem768 as $scheme;
+                    $body
+                }
+                Algorithm::MlKem1024 => {
+                    use mlkem1024 as $scheme;
+                    $body
+                }
+            }
+        };
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy, super::Serialize, super::Deserialize, Default)]
+    pub enum Algorithm {
+        MlKem512,
+        MlKem768,
+        #[default]
+        MlKem1024,
+    }
+
+    #[derive(Debug, Clone, super::Serialize, super::Deserialize)]
+    pub struct Keys {
+        pub algorithm: Algorithm,
+        pub secret: Option<Vec<u8>>,
+        pub public: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, super::Serialize, super::Deserialize)]
+    pub struct Encapsulated {
+        pub algorithm: Algorithm,
+        pub recipient: Vec<u8>,
+        pub cyphertext: Vec<u8>,
+    }
+
+    impl Keys {
+        pub fn new(algorithm: Algorithm) -> Self {
+            let (public, secret) = dispatch!(algorithm, scheme => {
+                let keypair = scheme::keypair();
+                (keypair.0.as_bytes().to_vec(), keypair.1.as_bytes().to_vec())
+            });
+            Self {
+                algorithm,
+                secret: Some(secret),
+                public,
+            }
+        }
+
+        pub fn encapsulate(&self) -> Result<(Vec<u8>, Encapsulated), String> {
+            let (shared, cyphertext) = dispatch!(self.algorithm, scheme => {
+                if let Ok(public) = scheme::PublicKey::from_bytes(&self.public) {
+                    let (shared, cyphertext) = scheme::encapsulate(&public);
+                    (shared.as_bytes().to_vec(), cyphertext.as_bytes().to_vec())
+                } else {
+                    return Err("Error while trying to reconstruct the public key!".to_string());
+                }
+            });
+            Ok((
+                shared,
+                Encapsulated {
+                    algorithm: self.algorithm,
+                    recipient: self.id(),
+                    cyphertext,
+                },
+            ))
+        }
+
+        pub fn decapsulate(&self, encapsulated: &Encapsulated) -> Result<Vec<u8>, String> {
+            if encapsulated.algorithm != self.algorithm || encapsulated.recipient != self.id() {
+                return Err(format!(
+                    "The crypt was encapsulated for {} ({}), not for {} ({})!",
+                    hex::encode(&encapsulated.recipient),
+                    encapsulated.algorithm,
+                    hex::encode(self.id()),
+                    self.algorithm
+                ));
+            }
+            let secret = match &self.secret {
+                Some(secret) => secret,
+                None => return Err("Unable to decapsulate without the secret key!".to_string()),
+            };
+            dispatch!(self.algorithm, scheme => {
+                match (
+                    scheme::SecretKey::from_bytes(secret),
+                    scheme::Ciphertext::from_bytes(&encapsulated.cyphertext),
+                ) {
+                    (Ok(secret), Ok(cyphertext)) => {
+                        Ok(scheme::decapsulate(&cyphertext, &secret).as_bytes().to_vec())
+                    }
+                    (Err(_), _) => {
+                        Err("Error while trying to reconstruct the secret key!".to_string())
+                    }
+                    (_, Err(_)) => {
+                        Err("Error while trying to reconstruct the encapsulated key!".to_string())
+                    }
+                }
+            })
+        }
+
+        pub fn id(&self) -> Vec<u8> {
+            blake3::hash(&self.public).as_bytes()[..consts::KEY_ID_SIZE.default as usize].to_vec()
+        }
+
+        pub fn public(&self) -> Self {
+            Self {
+                algorithm: self.algorithm,
+                secret: None,
+                public: self.public.clone(),
+            }
+        }
+    }
+}
+
+impl From<Signing::Algorithm> for u8 {
+    fn from(algorithm: Signing::Algorithm) -> Self {
+        match algorithm {
+            Signing::Algorithm::Dilithium5 => 0,
+            Signing::Algorithm::MlDsa44 => 1,
+            Signing::Algorithm::MlDsa65 => 2,
+            Signing::Algorithm::MlDsa87 => 3,
+        }
+    }
+}
+
+impl fmt::Display for Signing::Algorithm {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Dilithium5 => write!(formatter, "dilithium5"),
+            Self::MlDsa44 => write!(formatter, "ml-dsa-44"),
+            Self::MlDsa65 => write!(formatter, "ml-dsa-65"),
+            Self::MlDsa87 => write!(formatter, "ml-dsa-87"),
+        }
+    }
+}
+
+impl str::FromStr for Signing::Algorithm {
+    type Err = String;
+
+    fn from_str(algorithm: &str) -> Result<Self, Self::Err> {
+        match algorithm {
+            "dilithium5" => Ok(Self::Dilithium5),
+            "ml-dsa-44" => Ok(Self::MlDsa44),
+            "ml-dsa-65" => Ok(Self::MlDsa65),
+            "ml-dsa-87" => Ok(Self::MlDsa87),
+            _ => Err(format!(
+                "'{}' is not a signing algorithm (ml-dsa-44, ml-dsa-65, ml-dsa-87 or dilithium5)!",
+                algorithm
+            )),
+        }
+    }
+}
+
+/// New keys use ML-DSA-87, while keys and crypts that don't record their
+/// algorithm are read as the legacy Dilithium5 (see `Algorithm::legacy`).
+impl Default for Signing::Algorithm {
+    fn default() -> Self {
+        Self::MlDsa87
+    }
+}
+
+impl From<Kem::Algorithm> for u8 {
+    fn from(algorithm: Kem::Algorithm) -> Self {
+        match algorithm {
+            Kem::Algorithm::MlKem512 => 0,
+            Kem::Algorithm::MlKem768 => 1,
+            Kem::Algorithm::MlKem1024 => 2,
+        }
+    }
+}
+
+impl fmt::Display for Kem::Algorithm {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::MlKem512 => write!(formatter, "ml-kem-512"),
+            Self::MlKem768 => write!(formatter, "ml-kem-768"),
+            Self::MlKem1024 => write!(formatter, "ml-kem-1024"),
+        }
+    }
+}
+
+impl str::FromStr for Kem::Algorithm {
+    type Err = String;
+
+    fn from_str(algorithm: &str) -> Result<Self, Self::Err> {
+        match algorithm {
+            "ml-kem-512" => Ok(Self::MlKem512),
+            "ml-kem-768" => Ok(Self::MlKem768),
+            "ml-kem-1024" => Ok(Self::MlKem1024),
+            _ => Err(format!(
+                "'{}' is not a key encapsulation algorithm (ml-kem-512, ml-kem-768 or ml-kem-1024)!",
+                algorithm
+            )),
+        }
+    }
+}
+
+impl TryInto<Vec<u8>> for Kem::Keys {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryInto<Vec<u8>> for Signing::Keys<Option<Vec<u8>>> {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Kem::Keys {
+    type Error = flexbuffers::DeserializationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        flexbuffers::from_slice(&value)
+    }
+}
+
+impl From<Signing::Usage> for u8 {
+    fn from(usage: Signing::Usage) -> Self {
+        match usage {
+            Signing::Usage::Sign => 1,
+            Signing::Usage::Certify => 2,
+        }
+    }
+}
+
+impl fmt::Display for Signing::Usage {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Sign => write!(formatter, "sign"),
+            Self::Certify => write!(formatter, "certify"),
+        }
+    }
+}
+
+impl str::FromStr for Signing::Usage {
+    type Err = String;
+
+    fn from_str(usage: &str) -> Result<Self, Self::Err> {
+        match usage {
+            "sign" => Ok(Self::Sign),
+            "certify" => Ok(Self::Certify),
+            _ => Err(format!("'{}' is not a key usage (sign or certify)!", usage)),
+        }
+    }
+}
+
+impl Default for Cost {
     fn default() -> Self {
         Self {
-            salt: Default::default(),
-            cypher: Default::default(),
-            signing: None,
-            subKey: None,
+            memory: argon2::Params::DEFAULT_M_COST,
+            iterations: argon2::Params::DEFAULT_T_COST,
+            parallelism: argon2::Params::DEFAULT_P_COST,
         }
     }
 }
@@ -16,43 +16,125 @@
 
 #![allow(non_snake_case)]
 
+pub mod archive;
+pub mod armor;
+pub mod certificate;
+pub mod compression;
 pub mod consts;
+pub mod encoding;
 pub mod keys;
+pub mod metadata;
 pub mod ops;
+pub mod padding;
+pub mod report;
+pub mod revocation;
+pub mod shamir;
+pub mod signature;
 pub mod target;
+pub mod verify;
 
 use crate::{
-    keys::Keys,
-    ops::{exclusiveOR, keyedHash, randomness},
+    compression::Compression,
+    keys::{Kem, Keys, Signing},
+    metadata::Metadata,
+    ops::{exclusiveOR, keyedHash, prehash, randomness, timestamp},
+    padding::Padding,
+    report::{Check, Kind, Outcome, Report},
+    signature::Signature,
     target::Target,
+    verify::Verifier,
 };
 
-use flexbuffers;
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+pub const PREHASH_DOMAIN: &str = "Komatta 2022 prehashed crypt";
+pub const KEM_DOMAIN: &str = "Komatta 2022 ML-KEM shared secret";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Crypt {
     #[serde(skip_serializing, skip_deserializing)]
     pub target: Target,
     #[serde(skip_serializing, skip_deserializing)]
     pub keys: Keys,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub associated: Vec<u8>,
+    #[serde(default)]
+    version: u8,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub hashing: Hashing,
+    #[serde(default = "Signing::Algorithm::legacy")]
+    signing: Signing::Algorithm,
+    /// The ID of the primary signer's key, like countersignatures carry theirs.
+    #[serde(default)]
+    signer: Option<Vec<u8>>,
+    #[serde(default)]
+    encapsulated: Option<Kem::Encapsulated>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub level: Option<u16>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub padding: Padding,
+    #[serde(skip_serializing, skip_deserializing)]
+    metadata: Option<Metadata>,
     block_size: u16,
     iv: Vec<u8>,
     pub input: Vec<u8>,
     pub integrity: Integrity,
+    #[serde(default)]
+    pub signatures: Vec<Signature>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub verifier: Option<Verifier>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub reference: Option<u64>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub recipient: Option<Kem::Keys>,
+    #[serde(skip_serializing, skip_deserializing)]
+    shared: Option<Vec<u8>>,
+    #[serde(skip_serializing, skip_deserializing)]
+    report: Option<Report>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum Mode {
+    #[default]
+    Random,
+    Synthetic,
+}
+
+impl From<Mode> for u8 {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Random => 0,
+            Mode::Synthetic => 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum Hashing {
+    #[default]
+    Direct,
+    Prehashed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Integrity {
     Signed(Option<Vec<u8>>),
     Unsigned(Option<Vec<u8>>),
+    Both(Option<Vec<u8>>, Option<Vec<u8>>),
 }
 
-impl Into<u8> for Integrity {
-    fn into(self) -> u8 {
-        match self {
+impl From<Integrity> for u8 {
+    fn from(integrity: Integrity) -> Self {
+        match integrity {
             Integrity::Signed(_) => 0,
             Integrity::Unsigned(_) => 1,
+            Integrity::Both(..) => 2,
         }
     }
 }
@@ -62,6 +144,7 @@ impl From<u8> for Integrity {
         match byte {
             0 => Self::Signed(None),
             1 => Self::Unsigned(None),
+            2 => Self::Both(None, None),
             _ => panic!(
                 "'{}' is not a valid representation of an integrity type!",
                 byte
@@ -70,110 +153,458 @@ impl From<u8> for Integrity {
     }
 }
 
+impl fmt::Display for Integrity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Signed(_) => write!(formatter, "signed"),
+            Self::Unsigned(_) => write!(formatter, "unsigned"),
+            Self::Both(..) => write!(formatter, "both"),
+        }
+    }
+}
+
 impl Crypt {
     pub fn process(&mut self) -> Result<Vec<u8>, String> {
         match self.target {
             Target::Encrypt => {
-                let cyphered = self.cypher()?;
-                {
-                    let construction = self.construct(cyphered.clone());
-                    match self.integrity {
-                        Integrity::Signed(_) => {
-                            self.integrity =
-                                Integrity::Signed(Some(self.keys.signing()?.sign(construction)?));
-                        }
-                        Integrity::Unsigned(_) => {
-                            self.integrity = Integrity::Unsigned(Some(keyedHash(
-                                &construction,
+                self.shared = None;
+                self.encapsulated = None;
+                if let Some(recipient) = &self.recipient {
+                    let (shared, encapsulated) = recipient.encapsulate()?;
+                    self.encapsulated = Some(encapsulated);
+                    self.recipient = Some(recipient.public());
+                    self.shared = Some(self.share(shared)?);
+                }
+                if let Ok(signing) = self.keys.signing() {
+                    self.signing = signing.algorithm;
+                }
+                let plaintext = self.prepare()?;
+                if self.mode == Mode::Synthetic {
+                    self.iv = self.synthetic(&plaintext, self.iv.len() as u16)?;
+                }
+                let cyphered = self.cypher(&plaintext)?;
+                self.signer = match self.integrity {
+                    Integrity::Unsigned(_) => None,
+                    _ => Some(self.keys.signing()?.id()?),
+                };
+                match self.integrity {
+                    Integrity::Signed(_) => {
+                        self.integrity = Integrity::Signed(Some(
+                            self.keys.signing()?.sign(self.signable(&cyphered))?,
+                        ));
+                    }
+                    Integrity::Unsigned(_) => {
+                        self.integrity = Integrity::Unsigned(Some(keyedHash(
+                            &self.construct(cyphered.clone()),
+                            &self.keys.subKey()?,
+                            None,
+                        )));
+                    }
+                    Integrity::Both(..) => {
+                        self.integrity = Integrity::Both(
+                            Some(keyedHash(
+                                &self.construct(cyphered.clone()),
                                 &self.keys.subKey()?,
                                 None,
-                            )));
-                        }
+                            )),
+                            Some(self.keys.signing()?.sign(self.signable(&cyphered))?),
+                        );
                     }
                 }
                 Ok(cyphered)
             }
 
             Target::Decrypt => {
-                let construction = self.construct(self.input.clone());
-                if let Integrity::Signed(Some(integrity)) = self.integrity.clone() {
-                    if self.keys.signing()?.verify(construction, integrity)? {
-                        Ok(self.cypher()?)
-                    } else {
-                        Err("Invalid signature!".to_string())
-                    }
-                } else if let Integrity::Unsigned(Some(integrity)) = self.integrity.clone() {
-                    if integrity == keyedHash(&construction, &self.keys.subKey()?, None) {
-                        Ok(self.cypher()?)
-                    } else {
-                        Err("Invalid hash!".to_string())
-                    }
+                self.shared = None;
+                let report = self.verify();
+                let failure = report.failure();
+                self.report = Some(report);
+                if let Some(failure) = failure {
+                    return Err(failure);
+                }
+                let decyphered = self.cypher(&self.input.clone())?;
+                if self.mode == Mode::Synthetic
+                    && self.synthetic(&decyphered, self.iv.len() as u16)? != self.iv
+                {
+                    return Err("The synthetic IV doesn't match the plaintext!".to_string());
+                }
+                self.restore(decyphered)
+            }
+        }
+    }
+
+    pub fn verify(&mut self) -> Report {
+        let mut report = Report::default();
+        report.checks.push(Check::new(
+            Kind::Header,
+            match self.constraint() {
+                Ok(_) => Outcome::Passed,
+                Err(error) => Outcome::Failed(error),
+            },
+        ));
+        let mut primary = None;
+        match self.integrity.clone() {
+            Integrity::Signed(Some(signature)) => {
+                let check = self.checkSignature(signature);
+                if check.passed() {
+                    primary = check.signer.clone();
+                }
+                report.checks.push(check);
+            }
+            Integrity::Unsigned(Some(tag)) => {
+                report.checks.push(self.checkMac(tag));
+            }
+            Integrity::Both(Some(tag), Some(signature)) => {
+                let mac = self.checkMac(tag);
+                let check = if !mac.passed() {
+                    Check::new(
+                        Kind::Signature,
+                        Outcome::Skipped("the keyed hash didn't verify".to_string()),
+                    )
+                } else if self.keys.signing().is_err() {
+                    Check::new(
+                        Kind::Signature,
+                        Outcome::Skipped("no signing keys were provided".to_string()),
+                    )
                 } else {
-                    Err("Cannot verify integrity as it's not defined!".to_string())
+                    self.checkSignature(signature)
+                };
+                if check.passed() {
+                    primary = check.signer.clone();
                 }
+                report.checks.push(mac);
+                report.checks.push(check);
+            }
+            _ => report.checks.push(Check::new(
+                Kind::Header,
+                Outcome::Failed("Cannot verify integrity as it's not defined!".to_string()),
+            )),
+        }
+        if let Some(verifier) = &self.verifier {
+            report.checks.extend(verifier.check(
+                &self.construct(self.input.clone()),
+                primary,
+                &self.signatures,
+                self.reference.unwrap_or_else(timestamp),
+            ));
+        } else if !self.signatures.is_empty() {
+            report.checks.push(Check::new(
+                Kind::Countersignature,
+                Outcome::Skipped(format!(
+                    "{} countersignature(s) present but no keyring was provided",
+                    self.signatures.len()
+                )),
+            ));
+        }
+        report
+    }
+
+    pub fn report(&self) -> Option<&Report> {
+        self.report.as_ref()
+    }
+
+    pub fn encapsulated(&self) -> Option<&Kem::Encapsulated> {
+        self.encapsulated.as_ref()
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn signing(&self) -> Signing::Algorithm {
+        self.signing
+    }
+
+    /// The ID of the primary signer's key, unless the crypt predates it.
+    pub fn signer(&self) -> Option<&[u8]> {
+        self.signer.as_deref()
+    }
+
+    pub fn blockSize(&self) -> u16 {
+        self.block_size
+    }
+
+    pub fn ivSize(&self) -> usize {
+        self.iv.len()
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    fn constraint(&self) -> Result<&Self, String> {
+        if self.version > consts::FORMAT_VERSION {
+            return Err(format!("Unsupported format version {}!", self.version));
+        }
+        if self.version == 0 && (self.mode != Mode::Random || self.compression != Compression::None)
+        {
+            return Err("Legacy crypts can only use random IVs and no compression!".to_string());
+        }
+        if self.version == 0 && self.encapsulated.is_some() {
+            return Err("Legacy crypts can't be encapsulated for a recipient!".to_string());
+        }
+        consts::BLOCK_SIZE.check(self.block_size)?;
+        consts::IV_SIZE.check(self.iv.len() as u16)?;
+        Ok(self)
+    }
+
+    fn checkMac(&mut self, tag: Vec<u8>) -> Check {
+        let construction = self.construct(self.input.clone());
+        Check::new(
+            Kind::Mac,
+            match self.keys.subKey() {
+                Ok(subKey) if tag == keyedHash(&construction, &subKey, None) => Outcome::Passed,
+                Ok(_) => Outcome::Failed("Invalid hash!".to_string()),
+                Err(error) => Outcome::Failed(error),
+            },
+        )
+    }
+
+    fn checkSignature(&self, signature: Vec<u8>) -> Check {
+        let signing = match self.keys.signing() {
+            Ok(signing) => signing,
+            Err(error) => return Check::new(Kind::Signature, Outcome::Failed(error)),
+        };
+        if signing.algorithm != self.signing {
+            return Check::new(
+                Kind::Signature,
+                Outcome::Failed(format!(
+                    "The crypt was signed with {}, not with {}!",
+                    self.signing, signing.algorithm
+                )),
+            );
+        }
+        if let Err(error) = signing.algorithm.signatureSize().check(signature.len() as u16) {
+            return Check::new(Kind::Signature, Outcome::Failed(error));
+        }
+        let scheme = match self.hashing {
+            Hashing::Direct => signing.scheme(),
+            Hashing::Prehashed => format!("{}+blake3", signing.scheme()),
+        };
+        if let (Some(signer), Ok(id)) = (&self.signer, signing.id()) {
+            if *signer != id {
+                return Check::signed(
+                    Kind::Signature,
+                    Some(signer.clone()),
+                    Some(scheme),
+                    None,
+                    Outcome::Failed(format!(
+                        "The crypt was signed by the key {}, not by {}!",
+                        hex::encode(signer),
+                        hex::encode(id)
+                    )),
+                );
             }
         }
+        let at = self.reference.unwrap_or_else(timestamp);
+        // A master key verifies like its decrypt key, through the public key alone.
+        let verified = signing
+            .public()
+            .and_then(|signing| signing.verifyAt(self.signable(&self.input), signature, at));
+        let revoked = match (&self.verifier, &verified) {
+            (Some(verifier), Ok(true)) => verifier.revoked(&signing, self.reference),
+            _ => Ok(None),
+        };
+        // With trusted roots, the signer needs a chain to them like any countersigner.
+        let chained = match (&self.verifier, &verified) {
+            (Some(verifier), Ok(true)) => match &verifier.trust {
+                Some(trust) => signing
+                    .id()
+                    .and_then(|id| trust.resolve(&id, at, &verifier.revocations))
+                    .map(|_| ()),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        };
+        let mut check = Check::signed(
+            Kind::Signature,
+            signing.id().ok(),
+            Some(scheme),
+            None,
+            match (&verified, &revoked, &chained) {
+                (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+                    Outcome::Failed(error.clone())
+                }
+                (Ok(true), ..) => Outcome::Passed,
+                (Ok(false), ..) => Outcome::Failed("Invalid signature!".to_string()),
+            },
+        );
+        check.warning = revoked.unwrap_or_default();
+        check
+    }
+
+    fn signable(&self, cyphered: &[u8]) -> Vec<u8> {
+        match self.hashing {
+            Hashing::Direct => self.construct(cyphered.to_vec()),
+            Hashing::Prehashed => prehash(
+                PREHASH_DOMAIN.as_bytes(),
+                &[&self.header(), &self.iv, cyphered],
+            ),
+        }
     }
 
     fn construct(&self, cyphered: Vec<u8>) -> Vec<u8> {
-        [
+        [self.header(), self.iv.clone(), cyphered].concat()
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = [
             self.block_size.to_be_bytes().to_vec(),
             [Into::<u8>::into(self.integrity.clone())].to_vec(),
-            self.iv.clone(),
-            cyphered,
         ]
-        .concat()
+        .concat();
+        if self.version > 0 {
+            header.extend([self.version, self.mode.into(), self.compression.into()]);
+            header.extend((self.associated.len() as u64).to_be_bytes());
+            header.extend(self.associated.clone());
+            if let Some(encapsulated) = &self.encapsulated {
+                header.push(encapsulated.algorithm.into());
+                header.extend(encapsulated.recipient.clone());
+                header.extend((encapsulated.cyphertext.len() as u32).to_be_bytes());
+                header.extend(encapsulated.cyphertext.clone());
+            }
+        }
+        header
+    }
+
+    fn cypherKey(&mut self) -> Result<Vec<u8>, String> {
+        if let Some(key) = self.shared.clone() {
+            return Ok(key);
+        }
+        let shared = match (&self.encapsulated, &self.recipient) {
+            (None, _) => return self.keys.subKey(),
+            (Some(encapsulated), Some(recipient)) => recipient.decapsulate(encapsulated)?,
+            (Some(encapsulated), None) => {
+                return Err(format!(
+                "The crypt is encapsulated for the {} key {}, but no recipient key was provided!",
+                encapsulated.algorithm,
+                hex::encode(&encapsulated.recipient)
+            ))
+            }
+        };
+        let key = self.share(shared)?;
+        self.shared = Some(key.clone());
+        Ok(key)
+    }
+
+    fn share(&mut self, shared: Vec<u8>) -> Result<Vec<u8>, String> {
+        let subKey = self.keys.subKey()?;
+        Ok(keyedHash(
+            &[KEM_DOMAIN.as_bytes().to_vec(), shared].concat(),
+            &subKey,
+            Some(subKey.len() as u16),
+        ))
+    }
+
+    /// Derives the IV from the header (which covers the associated data) and
+    /// the prepared payload, so that any change to the compression, padding or
+    /// metadata yields another IV.
+    fn synthetic(&mut self, prepared: &[u8], size: u16) -> Result<Vec<u8>, String> {
+        let key = keyedHash(
+            b"Komatta synthetic IV",
+            &self.keys.subKey()?,
+            None,
+        );
+        Ok(keyedHash(
+            &[self.header().as_slice(), prepared].concat(),
+            &key,
+            Some(size),
+        ))
+    }
+
+    pub fn countersign(&mut self, keys: &Signing::Keys<Option<Vec<u8>>>) -> Result<(), String> {
+        let signature = Signature::sign(keys, &self.construct(self.input.clone()))?;
+        self.signatures.push(signature);
+        Ok(())
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn attach(&mut self, metadata: Metadata) {
+        self.metadata = Some(metadata);
+    }
+
+    fn prepare(&mut self) -> Result<Vec<u8>, String> {
+        let compressed = self.compression.compress(&self.input, self.level)?;
+        if self.version > 0 {
+            if let Some(metadata) = self.metadata.as_mut() {
+                metadata.size = self.input.len() as u64;
+            }
+            self.padding
+                .pad(&[Metadata::seal(&self.metadata)?, compressed].concat())
+        } else {
+            Ok(compressed)
+        }
+    }
+
+    fn restore(&mut self, decyphered: Vec<u8>) -> Result<Vec<u8>, String> {
+        let compressed = if self.version > 0 {
+            let (padding, unpadded) = Padding::unpad(&decyphered)?;
+            let (metadata, compressed) = Metadata::open(&unpadded)?;
+            self.padding = padding;
+            self.metadata = metadata;
+            compressed
+        } else {
+            decyphered
+        };
+        self.compression.decompress(&compressed)
     }
 
-    fn cypher(&mut self) -> Result<Vec<u8>, String> {
+    fn cypher(&mut self, input: &[u8]) -> Result<Vec<u8>, String> {
+        let key = self.cypherKey()?;
         match self.target {
             Target::Encrypt => {
-                let plaintext = self.input.chunks(self.block_size.into());
+                let plaintext = input.chunks(self.block_size.into());
                 let mut cyphertext: Vec<Vec<u8>> = vec![];
                 for (offset, block) in plaintext.enumerate() {
                     let lastEncryptedBlock = {
                         if let Some(lastBlock) = cyphertext.last() {
                             lastBlock.to_vec()
                         } else {
-                            keyedHash(&self.iv, &self.keys.subKey()?, None)
+                            keyedHash(&self.iv, &key, None)
                         }
                     };
 
                     let mut counter: Vec<u8> =
-                        keyedHash(&offset.to_be_bytes().to_vec(), &self.keys.subKey()?, None);
+                        keyedHash(&offset.to_be_bytes(), &key, None);
                     counter = exclusiveOR(&counter, &lastEncryptedBlock);
 
-                    cyphertext.push(exclusiveOR(&block.to_vec(), &counter));
+                    cyphertext.push(exclusiveOR(block, &counter));
                 }
                 Ok(cyphertext.concat())
             }
             Target::Decrypt => {
                 let mut lastEncryptedBlock: Option<Vec<u8>> = None;
                 let mut plaintext: Vec<Vec<u8>> = vec![];
-                for (offset, block) in self.input.chunks(self.block_size.into()).enumerate() {
+                for (offset, block) in input.chunks(self.block_size.into()).enumerate() {
                     let lastEncryptedBlockProcessed = {
                         if let Some(lastBlock) = lastEncryptedBlock {
                             lastBlock.to_vec()
                         } else {
-                            keyedHash(&self.iv, &self.keys.subKey()?, None)
+                            keyedHash(&self.iv, &key, None)
                         }
                     };
                     lastEncryptedBlock = Some(block.to_vec());
 
                     let mut counter: Vec<u8> =
-                        keyedHash(&offset.to_be_bytes().to_vec(), &self.keys.subKey()?, None);
+                        keyedHash(&offset.to_be_bytes(), &key, None);
                     counter = exclusiveOR(&counter, &lastEncryptedBlockProcessed);
 
-                    plaintext.push(exclusiveOR(&block.to_vec(), &counter));
+                    plaintext.push(exclusiveOR(block, &counter));
                 }
                 Ok(plaintext.concat())
             }
         }
     }
 
+    /// Rebuilds a crypt from its parts, written with the `version` of the
+    /// format (0 for the legacy crypts predating the versioned header).
     pub fn import(
         target: Target,
         keys: Keys,
+        version: u8,
         iv: Vec<u8>,
         block_size: Option<u16>,
         input: Vec<u8>,
@@ -181,7 +612,18 @@ impl Crypt {
     ) -> Self {
         Self {
             target,
-            keys: keys,
+            keys,
+            associated: vec![],
+            version,
+            mode: Mode::Random,
+            compression: Compression::None,
+            hashing: Hashing::Direct,
+            signing: Signing::Algorithm::legacy(),
+            signer: None,
+            encapsulated: None,
+            level: None,
+            padding: Padding::None,
+            metadata: None,
             block_size: {
                 if let Some(size) = block_size {
                     size
@@ -192,6 +634,12 @@ impl Crypt {
             iv,
             input,
             integrity,
+            signatures: vec![],
+            verifier: None,
+            reference: None,
+            recipient: None,
+            shared: None,
+            report: None,
         }
     }
 
@@ -205,7 +653,18 @@ impl Crypt {
     ) -> Self {
         Self {
             target,
-            keys: keys,
+            keys,
+            associated: vec![],
+            version: consts::FORMAT_VERSION,
+            mode: Mode::Random,
+            compression: Compression::None,
+            hashing: Hashing::Direct,
+            signing: Signing::Algorithm::legacy(),
+            signer: None,
+            encapsulated: None,
+            level: None,
+            padding: Padding::None,
+            metadata: None,
             block_size: {
                 if let Some(size) = block_size {
                     size
@@ -224,6 +683,12 @@ impl Crypt {
             },
             input,
             integrity,
+            signatures: vec![],
+            verifier: None,
+            reference: None,
+            recipient: None,
+            shared: None,
+            report: None,
         }
     }
 }
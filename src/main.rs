@@ -16,89 +16,197 @@
 
 #![allow(non_snake_case)]
 
-use Komatta::{consts::*, keys::Keys, target::Target, Crypt, Integrity};
+use Komatta::{
+    archive::{self, Entry},
+    armor::{self, Armor},
+    compression::Compression,
+    consts::*,
+    encoding::{Encoding, Transform},
+    keys::{Cost, Kem, Keys, Signing},
+    metadata::Metadata,
+    padding::Padding,
+    certificate::{Certificate, Trust},
+    report::{Check, Outcome},
+    revocation::{Reason, Revocation},
+    shamir::Share,
+    signature::Signature,
+    target::Target,
+    verify::{Policy, Verifier},
+    Crypt, Hashing, Integrity, Mode,
+};
 
-use base64::{engine::general_purpose::STANDARD as base64, Engine};
-use brotli;
-use clap::{Arg, ArgAction, Command};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use colored::*;
 use const_format::formatcp;
-use hex;
-use std::io::Cursor;
+use serde::Serialize;
+use serde_json::{self, json, Value};
 use std::{
+    env,
     ffi::OsString,
-    fs::File,
-    io::{BufWriter, Write},
-    os::unix::prelude::OsStrExt,
+    fmt,
+    fs::{self, File, OpenOptions},
+    path::Path,
+    process,
+    io::{self, Read, Write},
+    os::unix::{fs::OpenOptionsExt, prelude::OsStrExt},
     str::FromStr,
 };
 
-pub const OUT_FILE: &'static str = "output.txt";
+pub const SIGNATURE_EXTENSION: &str = "komsig";
 
-struct Transform {
-    value: Vec<u8>,
-    hex: bool,
+#[derive(Clone, Copy)]
+struct Format {
+    encoding: Encoding,
     compress: bool,
+    armored: bool,
 }
 
-impl Transform {
-    pub fn encode(&self) -> Result<Vec<u8>, String> {
-        let mut value = vec![];
-        if self.compress {
-            if let Err(_) = brotli::BrotliCompress(
-                &mut Cursor::new(self.value.clone()),
-                &mut value,
-                &brotli::enc::BrotliEncoderParams::default(),
-            ) {
-                return Err("Failed to compress!".to_string());
-            }
+impl Format {
+    pub fn transform(&self, value: Vec<u8>, kind: armor::Kind, id: Option<Vec<u8>>) -> Transform {
+        if self.armored {
+            Transform::armored(value, self.compress, kind, id)
         } else {
-            value = self.value.clone()
+            Transform::new(value, self.encoding, self.compress)
         }
-        Ok({
-            if self.hex {
-                hex::encode(value.clone()).as_bytes().to_vec()
-            } else {
-                base64.encode(value.clone()).as_bytes().to_vec()
-            }
-        })
     }
 
-    pub fn decode(&self) -> Result<Vec<u8>, String> {
-        if let Ok(decoded) = {
-            if self.hex {
-                if let Ok(hex) = hex::decode(self.value.clone()) {
-                    Ok(hex)
-                } else {
-                    Err(())
-                }
-            } else {
-                if let Ok(decoded) = base64.decode(self.value.clone()) {
-                    Ok(decoded)
-                } else {
-                    Err(())
-                }
-            }
-        } {
-            if self.compress {
-                let (mut compressed, mut decompressed) = (Cursor::new(decoded), vec![]);
-                if let Err(_) = brotli::BrotliDecompress(&mut compressed, &mut decompressed) {
-                    return Err("Failed to decompress!".to_string());
-                }
-                Ok(decompressed)
-            } else {
-                Ok(decoded)
-            }
+    pub fn parse(&self, value: Vec<u8>) -> Transform {
+        Transform::new(value, self.encoding, self.compress)
+    }
+
+    pub fn encode(
+        &self,
+        value: Vec<u8>,
+        kind: armor::Kind,
+        id: Option<Vec<u8>>,
+    ) -> Result<String, Failure> {
+        let encoded = self
+            .transform(value, kind, id)
+            .encode()
+            .map_err(Failure::Decode)?;
+        Ok(String::from_utf8_lossy(&encoded).to_string())
+    }
+
+    /// Decodes text given on the command line, naming what it was in errors.
+    pub fn decode(&self, value: &[u8], what: &str) -> Result<Vec<u8>, Failure> {
+        self.parse(value.trim_ascii().to_vec())
+            .decode()
+            .map_err(|error| Failure::Decode(format!("Failed to decode the {}: {}", what, error)))
+    }
+
+    pub fn keys(&self, value: &str) -> Result<Keys, Failure> {
+        let decoded = self
+            .decode(value.as_bytes(), "key")
+            .map_err(|failure| Failure::Key(failure.to_string()))?;
+        let mut keys = Keys::try_from(decoded)
+            .map_err(|_| Failure::Key("The key is malformed!".to_string()))?;
+        // Deriving the sub key (kept for later) checks the sizes and costs of the key.
+        keys.subKey()
+            .map_err(|error| Failure::Key(format!("The key is invalid: {}", error)))?;
+        Ok(keys)
+    }
+
+    /// Reads the signing keys of a master or decrypt key, or a signing public
+    /// key exported on its own, given as text or as a file containing it.
+    pub fn signing(&self, value: &str) -> Result<Signing::Keys<Option<Vec<u8>>>, Failure> {
+        let value = if Path::new(value).is_file() {
+            text(value)?
         } else {
-            Err("Failed to decode!".to_string())
+            value.to_string()
+        };
+        let decoded = self
+            .decode(value.as_bytes(), "key")
+            .map_err(|failure| Failure::Key(failure.to_string()))?;
+        match Keys::try_from(decoded.clone()) {
+            Ok(keys) => keys.signing(),
+            Err(_) => Signing::Keys::verifying(decoded),
         }
+        .map_err(Failure::Key)
     }
+}
 
-    pub fn new(value: Vec<u8>, hex: bool, compress: bool) -> Self {
+/// The result of `encrypt` or `decrypt`, printed as JSON with --json.
+#[derive(Default, Serialize)]
+struct Summary {
+    action: String,
+    ciphertext: Option<String>,
+    plaintext: Option<String>,
+    output: Option<String>,
+    masterKey: Option<String>,
+    masterKeyFile: Option<String>,
+    decryptKey: Option<String>,
+    decryptKeyFile: Option<String>,
+    keyId: Option<String>,
+    signingKeyId: Option<String>,
+    integrity: String,
+    blockSize: u16,
+    ivSize: usize,
+    metadata: Option<Metadata>,
+    verification: Option<Vec<Verification>>,
+    entries: Option<Vec<Entry>>,
+}
+
+#[derive(Serialize)]
+struct Verification {
+    kind: String,
+    outcome: String,
+    reason: Option<String>,
+    signer: Option<String>,
+    scheme: Option<String>,
+    timestamp: Option<u64>,
+    warning: Option<String>,
+}
+
+impl From<&Check> for Verification {
+    fn from(check: &Check) -> Self {
+        let (outcome, reason) = match &check.outcome {
+            Outcome::Passed => ("passed", None),
+            Outcome::Failed(reason) => ("failed", Some(reason.clone())),
+            Outcome::Skipped(reason) => ("skipped", Some(reason.clone())),
+        };
         Self {
-            value,
-            hex,
-            compress,
+            kind: check.kind.to_string(),
+            outcome: outcome.to_string(),
+            reason,
+            signer: check.signer.as_ref().map(hex::encode),
+            scheme: check.scheme.clone(),
+            timestamp: check.timestamp,
+            warning: check.warning.clone(),
+        }
+    }
+}
+
+/// Why the CLI failed, each kind exiting with its own code so that scripts
+/// can tell a tampered input from a typo.
+enum Failure {
+    /// The reason, along with the verification report when there's one.
+    Integrity(String, Option<Vec<Verification>>),
+    Usage(String),
+    Decode(String),
+    Key(String),
+    Io(String),
+}
+
+impl Failure {
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Integrity(..) => 1,
+            Self::Usage(_) => 2,
+            Self::Decode(_) => 3,
+            Self::Key(_) => 4,
+            Self::Io(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Integrity(error, _)
+            | Self::Usage(error)
+            | Self::Decode(error)
+            | Self::Key(error)
+            | Self::Io(error) => write!(formatter, "{}", error),
         }
     }
 }
@@ -108,137 +216,1216 @@ fn main() {
         .version(env!("CARGO_PKG_VERSION"))
         .subcommand_required(true)
         .args([
-            Arg::new("input")
-                .help("the string to process")
-                .required(true)
-                .value_parser(clap::value_parser!(OsString)),
             Arg::new("hex")
                 .help("encrypted data and keys will be encoded in Base16")
                 .long("hex")
+                .global(true)
                 .required(false)
                 .action(ArgAction::SetTrue),
+            Arg::new("encoding")
+                .help("encrypted data and keys will be encoded with this (base64, base64url, hex, base32, base58 or z85), decoding detects it where possible")
+                .long("encoding")
+                .conflicts_with("hex")
+                .global(true)
+                .required(false)
+                .default_value("base64")
+                .value_parser(Encoding::from_str),
             Arg::new("noCompression")
                 .help("encrypted data and keys won't be compressed")
                 .long("noCompression")
+                .global(true)
+                .required(false)
+                .action(ArgAction::SetTrue),
+            Arg::new("armor")
+                .help("encrypted data and keys will be ASCII-armored with line checksums")
+                .long("armor")
+                .conflicts_with("hex")
+                .global(true)
+                .required(false)
+                .action(ArgAction::SetTrue),
+            Arg::new("force")
+                .help("overwrite existing files")
+                .long("force")
+                .global(true)
                 .required(false)
                 .action(ArgAction::SetTrue),
         ])
         .subcommands([
-            Command::new("encrypt").args([
+            Command::new("encrypt")
+                .args(keySources())
+                .group(keySource().conflicts_with_all(["keySize", "sizes"]))
+                .args([
+                Arg::new("input")
+                    .help("the string to process (with --in or --dir, the only positional is the key)")
+                    .required_unless_present_any(["in", "dir"])
+                    .value_parser(clap::value_parser!(OsString)),
                 Arg::new("key")
-                    .help("the hex value used to encrypt the input")
+                    .help("the hex value used to encrypt the input (only with --insecureKey)")
                     .conflicts_with("keySize")
                     .conflicts_with("sizes")
                     .required(false)
                     .value_parser(clap::value_parser!(String)),
                 Arg::new("keySize")
-                    .help(formatcp!("if the key is missing, a randomly generated byte stream with the specified size will be generated (accepts {})", CRYPT_KEY_SIZE_VALUE))
+                    .help(formatcp!("if the key is missing, a randomly generated byte stream with the specified size will be generated (at least {} bytes)", CRYPT_KEY_SIZE_VALUE))
                     .long("keySize")
                     .conflicts_with("key")
                     .required(false)
-                    .value_parser(clap::value_parser!(u8)),
+                    .value_parser(clap::value_parser!(u16)),
                 Arg::new("noSigning")
                     .help("will disable signing")
                     .long("noSigning")
                     .required(false)
                     .action(ArgAction::SetTrue),
+                Arg::new("mac")
+                    .help("will authenticate with both the keyed hash and the signature")
+                    .long("mac")
+                    .conflicts_with("noSigning")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
                 Arg::new("sizes")
                     .help("set the sizes for the crypt")
                     .long("sizes")
                     .conflicts_with("key")
                     .required(false)
-                    .value_names(&["iv size", "block size", "cypher key salt size"])
+                    .value_names(["iv size", "block size", "cypher key salt size"])
                     .value_parser(clap::value_parser!(u16)),
+                Arg::new("synthetic")
+                    .help("derive the IV from the key, the associated data and the input (deterministic, misuse-resistant encryption)")
+                    .long("synthetic")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("prehash")
+                    .help("sign a Blake3 digest of the crypt instead of the whole crypt (for large inputs)")
+                    .long("prehash")
+                    .conflicts_with("noSigning")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("compressInput")
+                    .help("compress the input inside the crypt before encrypting it (none, brotli or zstd)")
+                    .long("compressInput")
+                    .required(false)
+                    .value_parser(clap::value_parser!(Compression)),
+                Arg::new("compressionLevel")
+                    .help("the compression level (brotli accepts 0..11, zstd accepts 1..22)")
+                    .long("compressionLevel")
+                    .requires("compressInput")
+                    .required(false)
+                    .value_parser(clap::value_parser!(u16)),
+                Arg::new("acceptCompressionRisk")
+                    .help("don't warn about compression oracles (only when the input doesn't mix secrets with attacker-controlled data)")
+                    .long("acceptCompressionRisk")
+                    .requires("compressInput")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("name")
+                    .help("the original file name, stored in the encrypted metadata")
+                    .long("name")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("contentType")
+                    .help("the MIME type of the input, stored in the encrypted metadata")
+                    .long("contentType")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("padding")
+                    .help("pad the input to hide its length (none, padme, power or bucket:<size>)")
+                    .long("padding")
+                    .required(false)
+                    .value_parser(clap::value_parser!(Padding)),
+                Arg::new("associated")
+                    .help("associated data authenticated along with the input (but not encrypted nor stored)")
+                    .long("associated")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("in")
+                    .help("read the input from this file, or from the standard input with '-'")
+                    .long("in")
+                    .value_name("path|-")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("dir")
+                    .help("pack this directory (relative paths, permissions and modification times) and encrypt it as one archive")
+                    .long("dir")
+                    .value_name("path")
+                    .conflicts_with("in")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("out")
+                    .help("write the output to this file, or to the standard output with '-' (the rest goes to the standard error)")
+                    .long("out")
+                    .value_name("path|-")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("raw")
+                    .help("write the crypt as raw bytes instead of encoded text (to the standard output, unless --out is given)")
+                    .long("raw")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("keyOut")
+                    .help("write the master key to this file instead of printing it")
+                    .long("keyOut")
+                    .value_name("path")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("decryptKeyOut")
+                    .help("write the decrypt key to this file instead of printing it")
+                    .long("decryptKeyOut")
+                    .value_name("path")
+                    .conflicts_with("noSigning")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("json")
+                    .help("print the result as a JSON object")
+                    .long("json")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("recipient")
+                    .help("the ML-KEM public key of the recipient, or a file containing it, whose secret key will also be needed to decrypt")
+                    .long("recipient")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
             ]),
-            Command::new("decrypt").arg(
+            Command::new("decrypt")
+                .args(keySources())
+                .group(keySource())
+                .args([
+                Arg::new("input")
+                    .help("the string to process (with --in, the only positional is the key)")
+                    .required_unless_present("in")
+                    .value_parser(clap::value_parser!(OsString)),
                 Arg::new("key")
-                    .help("the hex value used to decrypt the input")
-                    .required(true)
+                    .help("the hex value used to decrypt the input (only with --insecureKey)")
+                    .required(false)
                     .value_parser(clap::value_parser!(String)),
-            ),
+                Arg::new("associated")
+                    .help("associated data the input was encrypted with")
+                    .long("associated")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("recipient")
+                    .help("the ML-KEM secret key, if the input was encrypted for a recipient (only with --insecureKey)")
+                    .long("recipient")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("recipientFile")
+                    .help("read the ML-KEM secret key from this file, if the input was encrypted for a recipient")
+                    .long("recipientFile")
+                    .value_name("path")
+                    .conflicts_with("recipient")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("signers")
+                    .help("the signing public keys (or decrypt keys) of the countersigners, or files containing them")
+                    .long("signers")
+                    .num_args(1..)
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("roots")
+                    .help("the signing public keys of trusted roots, or files containing them: signers they certified are accepted")
+                    .long("roots")
+                    .num_args(1..)
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("certificates")
+                    .help("certificates linking signers to the trusted roots")
+                    .long("certificates")
+                    .num_args(1..)
+                    .requires("roots")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("policy")
+                    .help("the signatures required to accept the input: 'all', 'any' or how many of the signers")
+                    .long("policy")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("at")
+                    .help("verify the signing keys' validity at this Unix timestamp instead of now")
+                    .long("at")
+                    .required(false)
+                    .value_parser(clap::value_parser!(u64)),
+                Arg::new("revocations")
+                    .help("revocation certificates of signing keys that must no longer be trusted")
+                    .long("revocations")
+                    .num_args(1..)
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("in")
+                    .help("read the input from this file, or from the standard input with '-'")
+                    .long("in")
+                    .value_name("path|-")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("out")
+                    .help("write the output to this file, or to the standard output with '-' (the rest goes to the standard error)")
+                    .long("out")
+                    .value_name("path|-")
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("raw")
+                    .help("read the crypt as raw bytes instead of encoded text")
+                    .long("raw")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("json")
+                    .help("print the result as a JSON object")
+                    .long("json")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("restoreName")
+                    .help("write the output to its original file name (from the encrypted metadata)")
+                    .long("restoreName")
+                    .conflicts_with("out")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+                Arg::new("extract")
+                    .help("unpack the decrypted archive (from encrypt --dir) in this directory, once verified")
+                    .long("extract")
+                    .value_name("path")
+                    .conflicts_with_all(["out", "restoreName"])
+                    .required(false)
+                    .value_parser(clap::value_parser!(String)),
+                Arg::new("list")
+                    .help("list the files of the decrypted archive (from encrypt --dir), once verified")
+                    .long("list")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+            ]),
+            Command::new("split")
+                .about("splits the master key in shares so that any threshold of them can rebuild it")
+                .args(keySources())
+                .group(keySource())
+                .args([
+                    Arg::new("key")
+                        .help("the master key to split (only with --insecureKey)")
+                        .required(false)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("threshold")
+                        .help("the number of shares needed to rebuild the key (accepts 1..255)")
+                        .long("threshold")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u8)),
+                    Arg::new("shares")
+                        .help("the number of shares to generate (accepts 1..255)")
+                        .long("shares")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u8)),
+                    Arg::new("out")
+                        .help("write every share to '<prefix>.<index>.share' instead of printing them")
+                        .long("out")
+                        .value_name("prefix")
+                        .required(false)
+                        .value_parser(clap::value_parser!(String)),
+                ]),
+            Command::new("countersign")
+                .about("adds a countersignature to an encrypted input without changing its content")
+                .args(keySources())
+                .group(keySource())
+                .args([
+                    Arg::new("input")
+                        .help("the encrypted input")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("key")
+                        .help("the master key of the countersigner (only with --insecureKey)")
+                        .long("key")
+                        .required(false)
+                        .value_parser(clap::value_parser!(String)),
+                ]),
+            Command::new("sign")
+                .about("writes a detached signature of the file to '<file>.komsig'")
+                .args(keySources())
+                .group(keySource())
+                .args([
+                    Arg::new("file")
+                        .help("the file to sign")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("key")
+                        .help("the master key holding the signing secret key (only with --insecureKey)")
+                        .long("key")
+                        .required(false)
+                        .value_parser(clap::value_parser!(String)),
+                ]),
+            Command::new("verify")
+                .about("verifies a detached signature, exiting with 0 if it's valid or 1 otherwise")
+                .args([
+                    Arg::new("file")
+                        .help("the signed file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("signature")
+                        .help("the detached signature")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("key")
+                        .help("the signing public key, or the decrypt key holding it, or a file containing it")
+                        .long("key")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("at")
+                        .help("verify the signing keys' validity at this Unix timestamp instead of now")
+                        .long("at")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u64)),
+                    Arg::new("revocations")
+                        .help("revocation certificates of signing keys that must no longer be trusted")
+                        .long("revocations")
+                        .num_args(1..)
+                        .required(false)
+                        .value_parser(clap::value_parser!(String)),
+                ]),
+            Command::new("key")
+                .about("manages signing keys")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("revoke")
+                        .about("writes a revocation certificate for the signing key of a master key")
+                        .args(keySources())
+                        .group(keySource())
+                        .args([
+                            Arg::new("key")
+                                .help("the master key holding the signing secret key to revoke (only with --insecureKey)")
+                                .required(false)
+                                .value_parser(clap::value_parser!(String)),
+                            Arg::new("reason")
+                                .help("why the key is revoked: unspecified, compromised, superseded or retired")
+                                .long("reason")
+                                .required(false)
+                                .value_parser(clap::value_parser!(Reason)),
+                            Arg::new("at")
+                                .help("the Unix timestamp from which the key is revoked (defaults to now)")
+                                .long("at")
+                                .required(false)
+                                .value_parser(clap::value_parser!(u64)),
+                            Arg::new("out")
+                                .help("write the revocation certificate to this file instead of printing it")
+                                .long("out")
+                                .required(false)
+                                .value_parser(clap::value_parser!(String)),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("kem")
+                        .about("generates an ML-KEM key pair to receive crypts encrypted for a recipient")
+                        .args([
+                            Arg::new("algorithm")
+                                .help("the ML-KEM parameter set: ml-kem-512, ml-kem-768 or ml-kem-1024")
+                                .long("algorithm")
+                                .required(false)
+                                .value_parser(clap::value_parser!(Kem::Algorithm)),
+                            Arg::new("out")
+                                .help("write the secret key to '<prefix>.kem' and the public key to '<prefix>.kem.pub' instead of printing them")
+                                .long("out")
+                                .value_name("prefix")
+                                .required(false)
+                                .value_parser(clap::value_parser!(String)),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("certify")
+                        .about("certifies another signing public key with the signing key of a master key")
+                        .args(keySources())
+                        .group(keySource())
+                        .args([
+                            Arg::new("key")
+                                .help("the master key holding the issuer's signing secret key (only with --insecureKey)")
+                                .required(false)
+                                .value_parser(clap::value_parser!(String)),
+                            Arg::new("subject")
+                                .help("the signing public key to certify, or the decrypt key holding it, or a file containing it")
+                                .long("subject")
+                                .required(true)
+                                .value_parser(clap::value_parser!(String)),
+                            Arg::new("name")
+                                .help("the name the certified key belongs to")
+                                .long("name")
+                                .required(true)
+                                .value_parser(clap::value_parser!(String)),
+                            Arg::new("expires")
+                                .help("the Unix timestamp at which the certificate expires")
+                                .long("expires")
+                                .required(false)
+                                .value_parser(clap::value_parser!(u64)),
+                            Arg::new("out")
+                                .help("write the certificate to this file instead of printing it")
+                                .long("out")
+                                .required(false)
+                                .value_parser(clap::value_parser!(String)),
+                        ]),
+                ),
+            Command::new("keygen")
+                .about("generates a master key, its decrypt key and its signing public key, written to '<prefix>.key', '<prefix>.decrypt' and '<prefix>.pub'")
+                .args([
+                    Arg::new("out")
+                        .help("the prefix of the key files")
+                        .long("out")
+                        .value_name("prefix")
+                        .default_value("komatta")
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("saltSize")
+                        .help("the size of the Argon2 salt")
+                        .long("saltSize")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u16)),
+                    Arg::new("keySize")
+                        .help(formatcp!("the size of the cypher key (at least {} bytes)", CRYPT_KEY_SIZE_VALUE))
+                        .long("keySize")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u16)),
+                    Arg::new("noSigning")
+                        .help("will not generate signing keys (nor a decrypt key)")
+                        .long("noSigning")
+                        .required(false)
+                        .action(ArgAction::SetTrue),
+                    Arg::new("algorithm")
+                        .help("the signature scheme: ml-dsa-44, ml-dsa-65, ml-dsa-87 or dilithium5")
+                        .long("algorithm")
+                        .conflicts_with("noSigning")
+                        .required(false)
+                        .value_parser(clap::value_parser!(Signing::Algorithm)),
+                    Arg::new("expires")
+                        .help("when the signing key expires, as a Unix timestamp")
+                        .long("expires")
+                        .conflicts_with("noSigning")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u64)),
+                    Arg::new("usage")
+                        .help("what the signing key may be used for: sign, certify or both (comma-separated)")
+                        .long("usage")
+                        .conflicts_with("noSigning")
+                        .value_delimiter(',')
+                        .default_value("sign")
+                        .value_parser(clap::value_parser!(Signing::Usage)),
+                    Arg::new("memory")
+                        .help("the Argon2 memory cost in KiB")
+                        .long("memory")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u32)),
+                    Arg::new("iterations")
+                        .help("the Argon2 number of iterations")
+                        .long("iterations")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u32)),
+                    Arg::new("parallelism")
+                        .help("the Argon2 degree of parallelism")
+                        .long("parallelism")
+                        .required(false)
+                        .value_parser(clap::value_parser!(u32)),
+                ]),
+            Command::new("inspect")
+                .about("prints what a crypt or a key contains, without needing any key nor revealing secrets")
+                .args([
+                    Arg::new("input")
+                        .help("the crypt or key to inspect, or a file containing it")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("json")
+                        .help("print the fields as a JSON object")
+                        .long("json")
+                        .required(false)
+                        .action(ArgAction::SetTrue),
+                ]),
+            Command::new("combine")
+                .about("rebuilds the master key from its shares")
+                .arg(
+                    Arg::new("shares")
+                        .help("the shares (or files containing them) to combine")
+                        .required(true)
+                        .num_args(1..)
+                        .value_parser(clap::value_parser!(String)),
+                ),
         ])
         .get_matches();
 
-    let (hex, compress) = (
-        *parsed.get_one::<bool>("hex").unwrap_or_else(|| &false),
-        !*parsed
+    let format = Format {
+        encoding: if *parsed.get_one::<bool>("hex").unwrap_or(&false) {
+            Encoding::Hex
+        } else {
+            *parsed.get_one::<Encoding>("encoding").unwrap()
+        },
+        compress: !*parsed
             .get_one::<bool>("noCompression")
-            .unwrap_or_else(|| &false),
+            .unwrap_or(&false),
+        armored: *parsed.get_one::<bool>("armor").unwrap_or(&false),
+    };
+
+    let json = parsed
+        .subcommand()
+        .and_then(|(_, arguments)| arguments.try_get_one::<bool>("json").ok().flatten())
+        .is_some_and(|json| *json);
+    let result = match parsed.subcommand().unwrap() {
+        ("keygen", arguments) => keygen(arguments, format),
+        ("inspect", arguments) => inspect(arguments, format),
+        ("split", arguments) => split(arguments, format),
+        ("combine", arguments) => combine(arguments, format),
+        ("countersign", arguments) => countersign(arguments, format),
+        ("sign", arguments) => sign(arguments, format),
+        ("verify", arguments) => verify(arguments, format),
+        ("key", arguments) => match arguments.subcommand().unwrap() {
+            ("revoke", arguments) => revoke(arguments, format),
+            ("certify", arguments) => certify(arguments, format),
+            ("kem", arguments) => kem(arguments, format),
+            _ => unreachable!(),
+        },
+        (action, arguments) => crypt(Target::from_str(action).unwrap(), arguments, format),
+    };
+    if let Err(failure) = result {
+        if json {
+            let mut object = serde_json::json!({ "error": failure.to_string(), "code": failure.code() });
+            if let Failure::Integrity(_, Some(verification)) = &failure {
+                object["verification"] = json!(verification);
+            }
+            println!("{}", object);
+        } else {
+            eprintln!("{}", failure.to_string().bright_red());
+        }
+        process::exit(failure.code());
+    }
+}
+
+/// The ways to give a key without exposing it in the shell history or to `ps`.
+fn keySources() -> [Arg; 5] {
+    [
+        Arg::new("keyFile")
+            .help("read the key from this file")
+            .long("keyFile")
+            .value_name("path")
+            .required(false)
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("keyEnv")
+            .help("read the key from this environment variable")
+            .long("keyEnv")
+            .value_name("name")
+            .required(false)
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("keyFd")
+            .help("read the key from this inherited file descriptor")
+            .long("keyFd")
+            .visible_alias("key-fd")
+            .value_name("fd")
+            .required(false)
+            .value_parser(clap::value_parser!(u32)),
+        Arg::new("prompt")
+            .help("prompt for the key on the terminal without echoing it (the default when one is needed)")
+            .long("prompt")
+            .required(false)
+            .action(ArgAction::SetTrue),
+        Arg::new("insecureKey")
+            .help("accept the key as an argument, although it's exposed in the shell history and to ps")
+            .long("insecureKey")
+            .required(false)
+            .action(ArgAction::SetTrue),
+    ]
+}
+
+fn keySource() -> ArgGroup {
+    ArgGroup::new("keySource")
+        .args(["keyFile", "keyEnv", "keyFd", "prompt"])
+        .multiple(false)
+}
+
+fn keygen(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let defaults = Cost::default();
+    let cost = Cost {
+        memory: arguments
+            .get_one::<u32>("memory")
+            .copied()
+            .unwrap_or(defaults.memory),
+        iterations: arguments
+            .get_one::<u32>("iterations")
+            .copied()
+            .unwrap_or(defaults.iterations),
+        parallelism: arguments
+            .get_one::<u32>("parallelism")
+            .copied()
+            .unwrap_or(defaults.parallelism),
+    };
+    let signing = match *arguments.get_one::<bool>("noSigning").unwrap_or(&false) {
+        true => None,
+        false => Some(
+            arguments
+                .get_one::<Signing::Algorithm>("algorithm")
+                .copied()
+                .unwrap_or_default(),
+        ),
+    };
+    let mut keys = Keys::generate(
+        arguments.get_one::<u16>("saltSize").copied(),
+        arguments.get_one::<u16>("keySize").copied(),
+        signing,
+        arguments.get_one::<u64>("expires").copied(),
+        arguments
+            .get_many::<Signing::Usage>("usage")
+            .map(|usage| usage.copied().collect())
+            .unwrap_or_default(),
+        cost,
+    )
+    .map_err(Failure::Usage)?;
+    let id = keys.id().map_err(Failure::Key)?;
+    let prefix = arguments.get_one::<String>("out").unwrap();
+
+    let secret = format!("{}.key", prefix);
+    writeSecret(
+        &secret,
+        format
+            .encode(serialize(keys.clone())?, armor::Kind::Key, Some(id.clone()))?
+            .as_bytes(),
+        forced(arguments),
+    )?;
+    // The decrypt key holds the symmetric key, so it's as secret as the master key.
+    // Only the signing public key, written on its own, may be shared with anyone.
+    let (decrypt, verifying) = match keys.public() {
+        Ok(public) => {
+            let (path, signing) = (format!("{}.decrypt", prefix), public.signing().map_err(Failure::Key)?);
+            writeSecret(
+                &path,
+                format
+                    .encode(serialize(public)?, armor::Kind::Key, Some(id.clone()))?
+                    .as_bytes(),
+                forced(arguments),
+            )?;
+            let verifying = format!("{}.pub", prefix);
+            write(
+                &verifying,
+                format
+                    .encode(
+                        serialize(signing.clone())?,
+                        armor::Kind::PublicKey,
+                        Some(signing.id().map_err(Failure::Key)?),
+                    )?
+                    .as_bytes(),
+                forced(arguments),
+            )?;
+            (path.bright_blue().bold(), verifying.bright_blue().bold())
+        }
+        Err(_) => (
+            "Not available".bright_red().bold(),
+            "Not available".bright_red().bold(),
+        ),
+    };
+    println!(
+        "{} ({}) Master Key: {}, Decrypt Key: {}, Public Key: {}",
+        "Generated".red().bold().underline(),
+        hex::encode(id).bright_white(),
+        secret.bright_blue().bold(),
+        decrypt,
+        verifying
     );
+    Ok(())
+}
 
-    let action = Target::from_str(parsed.subcommand().unwrap().0).unwrap();
-    let params = {
-        match action {
-            Target::Encrypt => Some(parsed.subcommand().unwrap().1.get_many::<u16>("sizes")),
-            Target::Decrypt => None,
+fn inspect(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let input = arguments.get_one::<String>("input").unwrap();
+    let value = if Path::new(input).is_file() {
+        read(input)?
+    } else {
+        input.as_bytes().to_vec()
+    };
+    // Crypts written with --raw aren't encoded.
+    let decoded = format.decode(&value, "input").unwrap_or_else(|_| value.clone());
+    let armor = Armor::decode(&String::from_utf8_lossy(&value)).ok();
+    let header = |name: &str| {
+        armor
+            .as_ref()
+            .and_then(|armor| armor.header(name))
+            .map(str::to_string)
+    };
+
+    let fields = if let Ok(crypt) = Crypt::try_from(decoded.clone()) {
+        let (tag, signature) = match &crypt.integrity {
+            Integrity::Signed(signature) => (None, signature.as_ref()),
+            Integrity::Unsigned(tag) => (tag.as_ref(), None),
+            Integrity::Both(tag, signature) => (tag.as_ref(), signature.as_ref()),
+        };
+        vec![
+            ("type", json!("crypt")),
+            ("version", json!(crypt.version())),
+            ("keyId", json!(header("Key-ID"))),
+            ("mode", json!(format!("{:?}", crypt.mode).to_lowercase())),
+            ("compression", json!(crypt.compression.to_string())),
+            ("hashing", json!(format!("{:?}", crypt.hashing).to_lowercase())),
+            ("blockSize", json!(crypt.blockSize())),
+            ("ivSize", json!(crypt.ivSize())),
+            ("cyphertextSize", json!(crypt.input.len())),
+            ("integrity", json!(crypt.integrity.to_string())),
+            ("tagSize", json!(tag.map(Vec::len))),
+            ("signatureSize", json!(signature.map(Vec::len))),
+            ("signer", json!(crypt.signer().map(hex::encode))),
+            (
+                "signing",
+                json!(signature.map(|_| crypt.signing().to_string())),
+            ),
+            (
+                "recipient",
+                json!(crypt.encapsulated().map(|encapsulated| format!(
+                    "{} {}",
+                    encapsulated.algorithm,
+                    hex::encode(&encapsulated.recipient)
+                ))),
+            ),
+            (
+                "countersigners",
+                json!(crypt
+                    .signatures
+                    .iter()
+                    .map(|signature| hex::encode(&signature.signer))
+                    .collect::<Vec<String>>()),
+            ),
+        ]
+    } else if let Ok(mut keys) = Keys::try_from(decoded.clone()) {
+        let (signing, cost) = (keys.signing().ok(), keys.cost());
+        let validity = signing.as_ref().and_then(|signing| signing.validity.clone());
+        // The key ID runs Argon2 with the costs of the input, so only default ones are paid for.
+        let defaults = Cost::default();
+        let derivable = cost.memory <= defaults.memory
+            && cost.iterations <= defaults.iterations
+            && cost.parallelism <= defaults.parallelism;
+        vec![
+            ("type", json!("keys")),
+            (
+                "keyId",
+                json!(header("Key-ID").or_else(|| derivable
+                    .then(|| keys.id().ok().map(hex::encode))
+                    .flatten())),
+            ),
+            ("saltSize", json!(keys.saltSize())),
+            ("keySize", json!(keys.keySize())),
+            ("memory", json!(cost.memory)),
+            ("iterations", json!(cost.iterations)),
+            ("parallelism", json!(cost.parallelism)),
+            (
+                "signing",
+                json!(signing.as_ref().map(|signing| signing.scheme())),
+            ),
+            (
+                "signingKeyId",
+                json!(signing
+                    .as_ref()
+                    .and_then(|signing| signing.id().ok())
+                    .map(hex::encode)),
+            ),
+            (
+                "signingSecret",
+                json!(signing.as_ref().map(|signing| signing.secret.is_some())),
+            ),
+            (
+                "created",
+                json!(validity.as_ref().map(|validity| validity.created)),
+            ),
+            (
+                "expires",
+                json!(validity.as_ref().and_then(|validity| validity.expires)),
+            ),
+            (
+                "usage",
+                json!(validity.as_ref().map(|validity| validity
+                    .usage
+                    .iter()
+                    .map(|usage| usage.to_string())
+                    .collect::<Vec<String>>())),
+            ),
+        ]
+    } else if let Ok(keys) = Kem::Keys::try_from(decoded.clone()) {
+        vec![
+            ("type", json!("kem keys")),
+            ("keyId", json!(hex::encode(keys.id()))),
+            ("algorithm", json!(keys.algorithm.to_string())),
+            ("secret", json!(keys.secret.is_some())),
+        ]
+    } else if let Ok(signing) = Signing::Keys::verifying(decoded) {
+        let validity = signing.validity.clone();
+        vec![
+            ("type", json!("signing public key")),
+            ("keyId", json!(signing.id().ok().map(hex::encode))),
+            ("algorithm", json!(signing.scheme())),
+            (
+                "created",
+                json!(validity.as_ref().map(|validity| validity.created)),
+            ),
+            (
+                "expires",
+                json!(validity.as_ref().and_then(|validity| validity.expires)),
+            ),
+            (
+                "usage",
+                json!(validity.as_ref().map(|validity| validity
+                    .usage
+                    .iter()
+                    .map(|usage| usage.to_string())
+                    .collect::<Vec<String>>())),
+            ),
+        ]
+    } else {
+        return Err(Failure::Decode(
+            "The input is neither a crypt nor keys!".to_string(),
+        ));
+    };
+
+    if *arguments.get_one::<bool>("json").unwrap_or(&false) {
+        let object = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<serde_json::Map<String, Value>>();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&object)
+                .map_err(|_| Failure::Decode("Failed to serialize!".to_string()))?
+        );
+        return Ok(());
+    }
+    for (name, value) in fields {
+        let value = match value {
+            Value::Null => "none".to_string(),
+            Value::String(value) => value,
+            Value::Array(values) if values.is_empty() => "none".to_string(),
+            Value::Array(values) => values
+                .iter()
+                .map(|value| value.as_str().unwrap_or_default())
+                .collect::<Vec<&str>>()
+                .join(", "),
+            value => value.to_string(),
+        };
+        println!("{} {}", format!("{}:", name).red().bold(), value.bright_white());
+    }
+    Ok(())
+}
+
+fn split(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.keys(&secret(arguments)?)?;
+    let shares = keys
+        .split(
+            *arguments.get_one::<u8>("threshold").unwrap(),
+            *arguments.get_one::<u8>("shares").unwrap(),
+        )
+        .map_err(Failure::Usage)?;
+    let total = shares.len();
+
+    for share in shares {
+        let (index, id) = (share.index, share.id.clone());
+        let encoded = format.encode(serialize(share)?, armor::Kind::Share, Some(id))?;
+        if let Some(prefix) = arguments.get_one::<String>("out") {
+            // Any threshold of the shares is the master key, so they're kept as secret.
+            let path = format!("{}.{}.share", prefix, index);
+            writeSecret(&path, encoded.as_bytes(), forced(arguments))?;
+            println!(
+                "{} {}/{} -> {}",
+                "Share".red().bold().underline(),
+                index,
+                total,
+                path.bright_white()
+            );
+        } else {
+            println!(
+                "{} {}/{}: {}",
+                "Share".red().bold().underline(),
+                index,
+                total,
+                encoded.bright_blue().bold()
+            );
         }
+    }
+    Ok(())
+}
+
+fn combine(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let shares = arguments
+        .get_many::<String>("shares")
+        .unwrap()
+        .map(|share| {
+            let value = if Path::new(share).is_file() {
+                text(share)?
+            } else {
+                share.clone()
+            };
+            Share::try_from(format.decode(value.as_bytes(), "share")?)
+                .map_err(|_| Failure::Decode(format!("The share {} is malformed!", share)))
+        })
+        .collect::<Result<Vec<Share>, Failure>>()?;
+
+    let mut keys = Keys::combine(&shares).map_err(Failure::Key)?;
+    let masterKey = serialize(keys.clone())?;
+    println!(
+        "{} Master Key: {}",
+        "Combined".red().bold().underline(),
+        format
+            .encode(masterKey, armor::Kind::Key, keys.id().ok())?
+            .bright_blue()
+            .bold()
+    );
+    Ok(())
+}
+
+fn countersign(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.keys(&secret(arguments)?)?;
+    let mut crypt = Crypt::try_from(format.decode(
+        arguments.get_one::<String>("input").unwrap().as_bytes(),
+        "input",
+    )?)
+    .map_err(|_| Failure::Decode("The input is not a crypt!".to_string()))?;
+    crypt
+        .countersign(&keys.signing().map_err(Failure::Key)?)
+        .map_err(Failure::Key)?;
+    println!(
+        "{} Input ({} signatures): {}",
+        "Countersigned".red().bold().underline(),
+        crypt.signatures.len(),
+        format
+            .encode(serialize(crypt)?, armor::Kind::Message, None)?
+            .bright_blue()
+            .bold()
+    );
+    Ok(())
+}
+
+fn sign(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.keys(&secret(arguments)?)?;
+    let file = arguments.get_one::<String>("file").unwrap();
+    let signature = Signature::signStream(&keys.signing().map_err(Failure::Key)?, &mut open(file)?)
+        .map_err(Failure::Key)?;
+    let path = format!("{}.{}", file, SIGNATURE_EXTENSION);
+    write(
+        &path,
+        signature.armor().map_err(Failure::Decode)?.as_bytes(),
+        forced(arguments),
+    )?;
+    println!(
+        "{} {} -> {}",
+        "Signed".red().bold().underline(),
+        file.bright_white(),
+        path.bright_blue().bold()
+    );
+    Ok(())
+}
+
+fn verify(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.signing(arguments.get_one::<String>("key").unwrap())?;
+    let file = arguments.get_one::<String>("file").unwrap();
+    let signature = Signature::dearmor(&text(arguments.get_one::<String>("signature").unwrap())?)
+        .map_err(Failure::Decode)?;
+
+    let verified = match arguments.get_one::<u64>("at") {
+        Some(at) => signature.verifyStreamAt(&keys, &mut open(file)?, *at),
+        None => signature.verifyStream(&keys, &mut open(file)?),
     };
-    let keys = {
-        let key = parsed.subcommand().unwrap().1.get_one::<String>("key");
-        match action {
-            Target::Encrypt => {
-                if let Some(masterKey) = key {
-                    Keys::try_from(
-                        Transform::new(masterKey.as_bytes().to_vec(), hex, compress)
-                            .decode()
-                            .unwrap(),
-                    )
-                    .unwrap()
-                } else {
-                    Keys::new(
-                        {
-                            if let Some(mut parameters) = params.clone().unwrap() {
-                                match parameters.nth(2) {
-                                    Some(size) => Some(*size),
-                                    None => None,
-                                }
-                            } else {
-                                None
-                            }
-                        },
-                        None,
-                        !*parsed
-                            .subcommand()
-                            .unwrap()
-                            .1
-                            .get_one::<bool>("noSigning")
-                            .unwrap_or_else(|| &false),
-                    )
-                    .unwrap()
-                }
-            }
-            Target::Decrypt => {
-                if let Some(decryptKey) = key {
-                    Keys::try_from(
-                        Transform::new(decryptKey.as_bytes().to_vec(), hex, compress)
-                            .decode()
-                            .unwrap(),
-                    )
-                    .unwrap()
-                } else {
-                    panic!("Tried to decrypt without suplying the keys!")
-                }
+    let mut verifier = Verifier::new(vec![], Policy::All);
+    verifier.revocations = revocations(arguments)?;
+    let verified = verified.and_then(|verified| {
+        Ok((verified, verifier.revoked(&keys, signature.created)?))
+    });
+    match verified {
+        Ok((true, warning)) => {
+            println!(
+                "{} {} (signed by {}{})",
+                "Valid signature for".green().bold(),
+                file.bright_white(),
+                hex::encode(&signature.signer).bright_white(),
+                signature
+                    .created
+                    .map(|created| format!(" at {}", created))
+                    .unwrap_or_default()
+            );
+            if let Some(warning) = warning {
+                println!("{} {}", "Warning:".yellow().bold(), warning.bright_white());
             }
+            Ok(())
+        }
+        Ok((false, _)) => Err(Failure::Integrity(
+            format!("Invalid signature for {}!", file),
+            None,
+        )),
+        Err(error) => Err(Failure::Integrity(
+            format!("Invalid signature for {}: {}", file, error),
+            None,
+        )),
+    }
+}
+
+fn revoke(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.keys(&secret(arguments)?)?;
+    let revocation = Revocation::revoke(
+        &keys.signing().map_err(Failure::Key)?,
+        arguments
+            .get_one::<Reason>("reason")
+            .copied()
+            .unwrap_or_default(),
+        arguments.get_one::<u64>("at").copied(),
+    )
+    .map_err(Failure::Key)?;
+    let armored = revocation.armor().map_err(Failure::Decode)?;
+    if let Some(path) = arguments.get_one::<String>("out") {
+        write(path, armored.as_bytes(), forced(arguments))?;
+        println!(
+            "{} {} -> {}",
+            "Revoked".red().bold().underline(),
+            hex::encode(&revocation.key).bright_white(),
+            path.bright_blue().bold()
+        );
+    } else {
+        println!(
+            "{} {}:\n{}",
+            "Revoked".red().bold().underline(),
+            hex::encode(&revocation.key).bright_white(),
+            armored.bright_blue().bold()
+        );
+    }
+    Ok(())
+}
+
+fn certify(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = format.keys(&secret(arguments)?)?;
+    let subject = format.signing(arguments.get_one::<String>("subject").unwrap())?;
+    let certificate = Certificate::certify(
+        &keys.signing().map_err(Failure::Key)?,
+        &subject,
+        arguments.get_one::<String>("name").unwrap().clone(),
+        arguments.get_one::<u64>("expires").copied(),
+    )
+    .map_err(Failure::Key)?;
+    let armored = certificate.armor().map_err(Failure::Decode)?;
+    if let Some(path) = arguments.get_one::<String>("out") {
+        write(path, armored.as_bytes(), forced(arguments))?;
+        println!(
+            "{} {} ({}) -> {}",
+            "Certified".red().bold().underline(),
+            certificate.name.bright_white(),
+            hex::encode(&certificate.id).bright_white(),
+            path.bright_blue().bold()
+        );
+    } else {
+        println!(
+            "{} {} ({}):\n{}",
+            "Certified".red().bold().underline(),
+            certificate.name.bright_white(),
+            hex::encode(&certificate.id).bright_white(),
+            armored.bright_blue().bold()
+        );
+    }
+    Ok(())
+}
+
+fn kem(arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let keys = Kem::Keys::new(
+        arguments
+            .get_one::<Kem::Algorithm>("algorithm")
+            .copied()
+            .unwrap_or_default(),
+    );
+    let encode = |keys: Kem::Keys| {
+        let id = keys.id();
+        format.encode(serialize(keys)?, armor::Kind::Key, Some(id))
+    };
+    let (secret, public) = match arguments.get_one::<String>("out") {
+        Some(prefix) => {
+            let (secret, public) = (format!("{}.kem", prefix), format!("{}.kem.pub", prefix));
+            writeSecret(&secret, encode(keys.clone())?.as_bytes(), forced(arguments))?;
+            write(&public, encode(keys.public())?.as_bytes(), forced(arguments))?;
+            (secret, public)
         }
+        None => (encode(keys.clone())?, encode(keys.public())?),
     };
-    let input = match action {
-        Target::Encrypt => parsed
+    println!(
+        "{} {} ({}) Secret Key: {}, Public Key: {}",
+        "Generated".red().bold().underline(),
+        keys.algorithm.to_string().bright_white(),
+        hex::encode(keys.id()).bright_white(),
+        secret.bright_blue().bold(),
+        public.bright_blue().bold()
+    );
+    Ok(())
+}
+
+fn revocations(arguments: &ArgMatches) -> Result<Vec<Revocation>, Failure> {
+    arguments
+        .get_many::<String>("revocations")
+        .map(|paths| {
+            paths
+                .map(|path| Revocation::dearmor(&text(path)?).map_err(Failure::Decode))
+                .collect()
+        })
+        .unwrap_or(Ok(vec![]))
+}
+
+/// The signing keys of the decrypt keys given with the argument.
+fn signers(
+    arguments: &ArgMatches,
+    format: Format,
+    name: &str,
+) -> Result<Vec<Signing::Keys<Option<Vec<u8>>>>, Failure> {
+    arguments
+        .get_many::<String>(name)
+        .map(|keys| {
+            keys.map(|keys| format.signing(keys)).collect()
+        })
+        .unwrap_or(Ok(vec![]))
+}
+
+fn crypt(action: Target, arguments: &ArgMatches, format: Format) -> Result<(), Failure> {
+    let sizes = match action {
+        Target::Encrypt => arguments
+            .get_many::<u16>("sizes")
+            .map(|sizes| sizes.copied().collect())
+            .unwrap_or_default(),
+        Target::Decrypt => vec![],
+    };
+    let signed = match action {
+        Target::Encrypt => !*arguments
+            .get_one::<bool>("noSigning")
+            .unwrap_or(&false),
+        Target::Decrypt => false,
+    };
+
+    let directory = match action {
+        Target::Encrypt => arguments.get_one::<String>("dir"),
+        Target::Decrypt => None,
+    };
+
+    // With --in or --dir the input isn't positional, so the only positional is the key.
+    let source = arguments.get_one::<String>("in");
+    let positional = match source.or(directory) {
+        Some(_) if arguments.contains_id("key") => {
+            return Err(Failure::Usage(
+                "The input can't be given both with --in and as an argument!".to_string(),
+            ))
+        }
+        Some(_) => arguments
+            .get_one::<OsString>("input")
+            .map(|key| key.to_string_lossy().to_string()),
+        None => arguments.get_one::<String>("key").cloned(),
+    };
+    // Keys read from files, the environment or the terminal aren't printed back.
+    let given = positional.is_some();
+    let key = key(arguments, action, positional)?;
+    let echoed = given || key.is_none();
+    let keys = match (action, key) {
+        (_, Some(key)) => format.keys(&key)?,
+        (Target::Encrypt, None) => Keys::new(
+            sizes.get(2).copied(),
+            arguments.get_one::<u16>("keySize").copied(),
+            signed,
+        )
+        .map_err(Failure::Usage)?,
+        (Target::Decrypt, None) => {
+            return Err(Failure::Usage(
+                "The key used to decrypt the input is missing!".to_string(),
+            ))
+        }
+    };
+    let raw = *arguments.get_one::<bool>("raw").unwrap_or(&false);
+    let input = match (source, directory) {
+        (Some(source), _) => read(source)?,
+        (None, Some(directory)) => archive::pack(Path::new(directory)).map_err(Failure::Io)?,
+        (None, None) => arguments
             .get_raw("input")
             .unwrap()
             .next()
             .unwrap()
             .as_bytes()
             .to_vec(),
-        Target::Decrypt => {
-            let input = parsed
-                .get_raw("input")
-                .unwrap()
-                .next()
-                .unwrap()
-                .as_bytes()
-                .to_vec();
-            Transform::new(input, hex, compress).decode().unwrap()
-        }
+    };
+    let input = match action {
+        Target::Encrypt => input,
+        Target::Decrypt if raw => input,
+        Target::Decrypt => format.decode(&input, "input")?,
     };
 
     let mut crypt: Crypt;
@@ -248,49 +1435,351 @@ fn main() {
             crypt = Crypt::new(
                 action,
                 keys,
+                sizes.first().copied(),
+                sizes.get(1).copied(),
+                input.clone(),
                 {
-                    if let Some(mut parameters) = params.clone().unwrap() {
-                        match parameters.nth(0) {
-                            Some(size) => Some(*size),
-                            None => None,
-                        }
-                    } else {
-                        None
-                    }
-                },
-                {
-                    if let Some(mut parameters) = params.clone().unwrap() {
-                        match parameters.nth(1) {
-                            Some(size) => Some(*size),
-                            None => None,
+                    match signed {
+                        true => {
+                            if *arguments.get_one::<bool>("mac").unwrap_or(&false) {
+                                Integrity::Both(None, None)
+                            } else {
+                                Integrity::Signed(None)
+                            }
                         }
-                    } else {
-                        None
+                        false => Integrity::Unsigned(None),
                     }
                 },
-                input.clone(),
-                {
-                    match !*parsed
-                        .subcommand()
-                        .unwrap()
-                        .1
-                        .get_one::<bool>("noSigning")
-                        .unwrap_or_else(|| &false)
+            );
+            if *arguments.get_one::<bool>("synthetic").unwrap_or(&false) {
+                crypt.mode = Mode::Synthetic;
+            }
+            if *arguments.get_one::<bool>("prehash").unwrap_or(&false) {
+                crypt.hashing = Hashing::Prehashed;
+            }
+            if let Some(padding) = arguments.get_one::<Padding>("padding") {
+                crypt.padding = *padding;
+            }
+            let (name, contentType) = (
+                arguments.get_one::<String>("name"),
+                arguments.get_one::<String>("contentType"),
+            );
+            if let Some(directory) = directory {
+                crypt.attach(Metadata::new(
+                    name.cloned().or(Path::new(directory)
+                        .canonicalize()
+                        .ok()
+                        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))),
+                    Some(contentType.cloned().unwrap_or(archive::CONTENT_TYPE.to_string())),
+                ));
+            } else {
+                // Files read with --in keep their name, so that --restoreName brings it back.
+                let name = name.cloned().or(source
+                    .filter(|source| *source != "-")
+                    .and_then(|source| Path::new(source).file_name())
+                    .map(|name| name.to_string_lossy().to_string()));
+                if name.is_some() || contentType.is_some() {
+                    crypt.attach(Metadata::new(name, contentType.cloned()));
+                }
+            }
+            if let Some(compression) = arguments.get_one::<Compression>("compressInput") {
+                crypt.compression = *compression;
+                crypt.level = arguments.get_one::<u16>("compressionLevel").copied();
+                if let Some(warning) = compression.warning() {
+                    if !*arguments
+                        .get_one::<bool>("acceptCompressionRisk")
+                        .unwrap_or(&false)
                     {
-                        true => Integrity::Signed(None),
-                        false => Integrity::Unsigned(None),
+                        eprintln!("{} {}", "Warning:".yellow().bold(), warning.yellow());
                     }
-                },
-            )
+                }
+            }
         }
         Target::Decrypt => {
-            crypt = Crypt::try_from(input.clone()).unwrap();
+            crypt = Crypt::try_from(input.clone())
+                .map_err(|_| Failure::Decode("The input is not a crypt!".to_string()))?;
             crypt.target = Target::Decrypt;
             crypt.keys = keys;
+            // Another key pair's decrypt key is a key error, not a sign of tampering.
+            if let Some(signer) = crypt.signer() {
+                let id = crypt.keys.signing().and_then(|signing| signing.id()).map_err(Failure::Key)?;
+                if id != signer {
+                    return Err(Failure::Key(format!(
+                        "The input was signed by the key {}, not by the given one ({})!",
+                        hex::encode(signer),
+                        hex::encode(id)
+                    )));
+                }
+            }
         }
     }
 
-    println!(
+    if let Some(associated) = arguments.get_one::<String>("associated") {
+        crypt.associated = associated.as_bytes().to_vec();
+    }
+    // Only the recipient's secret key, when decrypting, needs keeping off the command line.
+    let recipient = match (action, arguments.get_one::<String>("recipient")) {
+        (Target::Decrypt, Some(_)) if !insecure(arguments) => {
+            return Err(Failure::Usage(
+                "Keys given as arguments are exposed in the shell history and to ps, use --recipientFile instead (or --insecureKey)!"
+                    .to_string(),
+            ))
+        }
+        (_, Some(recipient)) if Path::new(recipient).is_file() => Some(text(recipient)?),
+        (_, Some(recipient)) => Some(recipient.clone()),
+        (Target::Decrypt, None) => match arguments.get_one::<String>("recipientFile") {
+            Some(path) => Some(text(path)?),
+            None => None,
+        },
+        (Target::Encrypt, None) => None,
+    };
+    if let Some(recipient) = recipient {
+        crypt.recipient = Some(
+            Kem::Keys::try_from(format.decode(recipient.as_bytes(), "recipient key")?)
+                .map_err(|_| Failure::Key("The recipient key is malformed!".to_string()))?,
+        );
+    }
+    if action == Target::Decrypt {
+        if let (Some(encapsulated), None) = (crypt.encapsulated(), &crypt.recipient) {
+            return Err(Failure::Key(format!(
+                "The crypt is encapsulated for the {} key {}, but no recipient key was provided!",
+                encapsulated.algorithm,
+                hex::encode(&encapsulated.recipient)
+            )));
+        }
+        let (signers, roots) = (
+            signers(arguments, format, "signers")?,
+            signers(arguments, format, "roots")?,
+        );
+        if !signers.is_empty() || !roots.is_empty() {
+            let keyring = signers;
+            let policy = match arguments.get_one::<String>("policy").map(String::as_str) {
+                None | Some("all") => Policy::All,
+                Some("any") => Policy::Any,
+                Some(threshold) => Policy::Threshold(
+                    threshold.parse().map_err(|_| {
+                        Failure::Usage(format!(
+                            "The policy '{}' is neither 'all', 'any' nor a number!",
+                            threshold
+                        ))
+                    })?,
+                    keyring
+                        .iter()
+                        .map(|keys| keys.id())
+                        .collect::<Result<Vec<Vec<u8>>, String>>()
+                        .map_err(Failure::Key)?,
+                ),
+            };
+            let mut verifier = Verifier::new(keyring, policy);
+            if !roots.is_empty() {
+                let certificates = arguments
+                    .get_many::<String>("certificates")
+                    .map(|paths| {
+                        paths
+                            .map(|path| Certificate::dearmor(&text(path)?).map_err(Failure::Decode))
+                            .collect()
+                    })
+                    .unwrap_or(Ok(vec![]))?;
+                verifier.trust = Some(Trust::new(roots, certificates));
+            }
+            crypt.verifier = Some(verifier);
+        }
+        let revocations = revocations(arguments)?;
+        if !revocations.is_empty() && !matches!(crypt.integrity, Integrity::Unsigned(_)) {
+            crypt
+                .verifier
+                .get_or_insert_with(|| Verifier::new(vec![], Policy::Any))
+                .revocations = revocations;
+        }
+        crypt.reference = arguments.get_one::<u64>("at").copied();
+    }
+    let restoreName = action == Target::Decrypt
+        && *arguments
+            .get_one::<bool>("restoreName")
+            .unwrap_or(&false);
+
+    // Raw bytes can't be printed along with the keys, so they default to the standard output.
+    let destination = arguments
+        .get_one::<String>("out")
+        .map(String::as_str)
+        .or((raw && action == Target::Encrypt).then_some("-"));
+    let piped = destination == Some("-");
+    let emit = |line: String| {
+        if piped {
+            eprintln!("{}", line)
+        } else {
+            println!("{}", line)
+        }
+    };
+    let named = |path: &str| match path {
+        "-" => "the standard output".to_string(),
+        path => format!("the {} file", path),
+    };
+    let id = crypt.keys.clone().id().ok();
+    let json = *arguments.get_one::<bool>("json").unwrap_or(&false);
+
+    let output = match crypt.target {
+        Target::Encrypt => {
+            let mut out = crypt.clone();
+            out.input = out.process().map_err(Failure::Usage)?;
+            let encrypted = serialize(out)?;
+            if raw {
+                encrypted
+            } else {
+                format
+                    .encode(encrypted, armor::Kind::Message, id.clone())?
+                    .into_bytes()
+            }
+        }
+        Target::Decrypt => match crypt.process() {
+            Ok(decrypted) => decrypted,
+            Err(error) => {
+                return match crypt.report() {
+                    Some(report) if !report.passed() => {
+                        if !json {
+                            emit(format!(
+                                "{}\n{}",
+                                "Verification:".red().bold(),
+                                report.to_string().bright_white()
+                            ));
+                        }
+                        Err(Failure::Integrity(
+                            error,
+                            Some(report.checks.iter().map(Verification::from).collect()),
+                        ))
+                    }
+                    _ => Err(Failure::Decode(error)),
+                }
+            }
+        },
+    };
+    let mut summary = Summary {
+        action: crypt.target.to_string(),
+        keyId: id.as_ref().map(hex::encode),
+        signingKeyId: crypt
+            .keys
+            .signing()
+            .and_then(|keys| keys.id())
+            .ok()
+            .map(hex::encode),
+        integrity: crypt.integrity.to_string(),
+        blockSize: crypt.blockSize(),
+        ivSize: crypt.ivSize(),
+        ..Default::default()
+    };
+    let (extract, listed) = match action {
+        Target::Encrypt => (None, false),
+        Target::Decrypt => (
+            arguments.get_one::<String>("extract"),
+            *arguments.get_one::<bool>("list").unwrap_or(&false),
+        ),
+    };
+    // Only reached once the crypt is verified, and nothing is extracted unless every entry is safe.
+    if extract.is_some() || listed {
+        summary.entries = Some(archive::entries(&output).map_err(Failure::Decode)?);
+    }
+    let result = if let Some(extract) = extract {
+        archive::unpack(&output, Path::new(extract), forced(arguments)).map_err(Failure::Io)?;
+        summary.output = Some(extract.clone());
+        format!("{}", format!("The archive was extracted in the {} directory!", extract.bright_white()).bright_red())
+    } else if let Some(destination) = destination {
+        write(destination, &output, forced(arguments))?;
+        summary.output = Some(destination.to_string());
+        format!("{}", format!("The output was written to {}!", named(destination).bright_white()).bright_red())
+    } else if listed {
+        format!(
+            "{}",
+            format!("The archive has {} entries!", summary.entries.iter().flatten().count()).bright_red()
+        )
+    } else if crypt.target == Target::Encrypt {
+        let ciphertext = String::from_utf8_lossy(&output).to_string();
+        summary.ciphertext = Some(ciphertext.clone());
+        ciphertext
+    } else if let Some(name) = crypt
+        .metadata()
+        .and_then(|metadata| metadata.name.clone())
+        .and_then(|name| Path::new(&name).file_name().map(|name| name.to_os_string()))
+        .filter(|_| restoreName)
+    {
+        write(&name.to_string_lossy(), &output, forced(arguments))?;
+        summary.output = Some(name.to_string_lossy().to_string());
+        format!("{}", format!("The output was restored in the {} file!", name.to_string_lossy().bright_white()).bright_red())
+    } else if let Ok(decoded) = String::from_utf8(output.clone()) {
+        summary.plaintext = Some(decoded.clone());
+        decoded
+    } else {
+        return Err(Failure::Usage(
+            "The output couldn't be UTF-8 encoded, write it as raw bytes with --out <path|->!"
+                .to_string(),
+        ));
+    };
+
+    let masterKey = match crypt.keys.signing() {
+        Ok(Signing::Keys { secret: None, .. }) => None,
+        _ => Some(format.encode(serialize(crypt.keys.clone())?, armor::Kind::Key, id.clone())?),
+    };
+    let decryptKey = match crypt.integrity {
+        Integrity::Signed(_) | Integrity::Both(..) => Some(format.encode(
+            serialize(crypt.keys.public().map_err(Failure::Key)?)?,
+            armor::Kind::Key,
+            id.clone(),
+        )?),
+        Integrity::Unsigned(_) => None,
+    };
+    // Keys written to their own files are left out of the output. Both hold the
+    // symmetric key, so only their owner may read them.
+    let saved = |name: &str, key: Option<String>| match (action, key) {
+        (Target::Encrypt, Some(key)) => match arguments.get_one::<String>(name) {
+            Some(path) => {
+                writeSecret(path, key.as_bytes(), forced(arguments))?;
+                Ok((None, Some(path.clone())))
+            }
+            None => Ok((Some(key).filter(|_| echoed), None)),
+        },
+        (_, key) => Ok::<_, Failure>((key.filter(|_| echoed), None)),
+    };
+    (summary.masterKey, summary.masterKeyFile) = saved("keyOut", masterKey)?;
+    (summary.decryptKey, summary.decryptKeyFile) = saved("decryptKeyOut", decryptKey)?;
+    if crypt.target == Target::Decrypt {
+        summary.metadata = crypt.metadata().cloned();
+        summary.verification = crypt
+            .report()
+            .map(|report| report.checks.iter().map(Verification::from).collect());
+    }
+
+    if json {
+        emit(
+            serde_json::to_string_pretty(&summary)
+                .map_err(|_| Failure::Decode("Failed to serialize!".to_string()))?,
+        );
+        return Ok(());
+    }
+    let described = match (crypt.target, source.or(directory)) {
+        (_, Some(source)) => format!(
+            "{} bytes from {}",
+            input.len(),
+            match source.as_str() {
+                "-" => "the standard input",
+                source => source,
+            }
+        ),
+        (Target::Encrypt, None) => {
+            if let Ok(decoded) = String::from_utf8(crypt.input.clone()) {
+                decoded
+            } else {
+                format!("{}", "The input couldn't be UTF-8 encoded!".bright_red().bold())
+            }
+        }
+        (Target::Decrypt, None) => format.encode(input.clone(), armor::Kind::Message, id.clone())?,
+    };
+    let shown = |key: &Option<String>, file: &Option<String>| match (key, file) {
+        (Some(key), _) => key.clone(),
+        (None, Some(file)) => format!("{}", format!("written to {}", named(file)).bright_red()),
+        (None, None) if !echoed => format!("{}", "Not shown".bright_red().bold()),
+        (None, None) => format!("{}", "Not available".bright_red().bold()),
+    };
+
+    emit(format!(
         "{} Input: {}, Master Key: {}, Decrypt Key: {} -> {}",
         match crypt.target {
             Target::Encrypt => "Encrypted",
@@ -299,77 +1788,197 @@ fn main() {
         .red()
         .bold()
         .underline(),
-        match crypt.target {
-            Target::Encrypt => {
-                if let Ok(decoded) = String::from_utf8(crypt.input.clone()) {
-                    decoded
-                } else {
-                    format!("{}", "The input couldn't be UTF-8 encoded!".bright_red().bold())
-                }
-            },
-            Target::Decrypt => {
-                String::from_utf8(Transform::new(input.clone(), hex, compress)
-                .encode()
-                .unwrap()).unwrap()
-            },
-        }
-        .bright_white(),
-        {
-            let masterKey = TryInto::<Vec<u8>>::try_into(crypt.keys.clone()).unwrap();
-            if let Ok(keys) = crypt.keys.signing() {
-                if let Some(_) = keys.secret {
-                    String::from_utf8(Transform::new(masterKey, hex, compress)
-                    .encode()
-                    .unwrap()).unwrap()
-                } else {
-                    format!("{}", "Not available".bright_red().bold())
-                }
-            } else{
-                String::from_utf8(Transform::new(masterKey, hex, compress)
-                    .encode()
-                    .unwrap()).unwrap()
-            }
+        described.bright_white(),
+        shown(&summary.masterKey, &summary.masterKeyFile).bright_white(),
+        shown(&summary.decryptKey, &summary.decryptKeyFile).bright_white(),
+        result.bright_blue().bold().underline()
+    ));
+
+    if let Some(metadata) = &summary.metadata {
+        emit(format!("{} {}", "Metadata:".red().bold(), metadata.to_string().bright_white()));
+    }
+    if let (Target::Decrypt, Some(report)) = (crypt.target, crypt.report()) {
+        emit(format!("{}\n{}", "Verification:".red().bold(), report.to_string().bright_white()));
+    }
+    if let Some(entries) = &summary.entries {
+        let lines = entries.iter().map(Entry::to_string).collect::<Vec<String>>();
+        emit(format!("{}\n{}", "Entries:".red().bold(), lines.join("\n").bright_white()));
+    }
+    Ok(())
+}
+
+/// Reads the key of `encrypt` or `decrypt` from where it was given, prompting
+/// for it on the terminal when decrypting without one.
+fn key(
+    arguments: &ArgMatches,
+    action: Target,
+    positional: Option<String>,
+) -> Result<Option<String>, Failure> {
+    let prompted = *arguments.get_one::<bool>("prompt").unwrap_or(&false);
+    if let Some(key) = positional {
+        if prompted || ["keyFile", "keyEnv", "keyFd"].iter().any(|id| arguments.contains_id(id)) {
+            return Err(Failure::Usage(
+                "The key can't be given both as an argument and from elsewhere!".to_string(),
+            ));
         }
-        .bright_white(),
-        match crypt.integrity {
-            Integrity::Signed(_) => {
-                let decryptKey = TryInto::<Vec<u8>>::try_into(crypt.keys.clone().public().unwrap()).unwrap();
-                String::from_utf8(Transform::new(decryptKey, hex, compress)
-                    .encode()
-                    .unwrap()).unwrap()
-            },
-            Integrity::Unsigned(_) => format!("{}", "Not available".bright_red().bold())
+        if !insecure(arguments) {
+            return Err(Failure::Usage(
+                "Keys given as arguments are exposed in the shell history and to ps, use --keyFile, --keyEnv, --keyFd or --prompt instead (or --insecureKey)!"
+                    .to_string(),
+            ));
         }
-        .bright_white(),
-        match crypt.target {
-            Target::Encrypt => {
-                let encrypted = TryInto::<Vec<u8>>::try_into({
-                    let mut out = crypt.clone();
-                    out.input = out.process().unwrap();
-                    out
-                });
-                String::from_utf8(Transform::new(encrypted.unwrap(), hex, compress)
-                    .encode()
-                    .unwrap()).unwrap()
-            },
-            Target::Decrypt => {
-                match crypt.process() {
-                    Ok(decrypted) => {
-                        if let Ok(decoded) = String::from_utf8(decrypted.clone()) {
-                            decoded
-                        } else {
-                            let mut writeBuffer = BufWriter::new(File::create(OUT_FILE).unwrap());
-                            writeBuffer.write_all(decrypted.as_slice()).unwrap();
-                            writeBuffer.flush().unwrap();
-                            format!("{}", format!("The output couldn't be UTF-8 encoded, so the output was written as raw bytes in the {} file!", OUT_FILE.bright_white()).bright_red())
-                        }
+        return Ok(Some(key));
+    }
+    if let Some(path) = arguments.get_one::<String>("keyFile") {
+        return Ok(Some(text(path)?));
+    }
+    if let Some(name) = arguments.get_one::<String>("keyEnv") {
+        return match env::var(name) {
+            Ok(key) => Ok(Some(key)),
+            Err(_) => Err(Failure::Usage(format!(
+                "The {} environment variable is not set!",
+                name
+            ))),
+        };
+    }
+    if let Some(descriptor) = arguments.get_one::<u32>("keyFd") {
+        // Inherited descriptors are reachable through /dev/fd, without unsafe code.
+        return Ok(Some(text(&format!("/dev/fd/{}", descriptor))?));
+    }
+    if action == Target::Encrypt && !prompted {
+        return Ok(None);
+    }
+
+    let key = prompt("Key: ")?;
+    if action == Target::Encrypt && prompt("Confirm the key: ")? != key {
+        return Err(Failure::Usage("The keys don't match!".to_string()));
+    }
+    Ok(Some(key))
+}
+
+/// Reads the master key of the commands other than `encrypt` and `decrypt`,
+/// which always need one.
+fn secret(arguments: &ArgMatches) -> Result<String, Failure> {
+    key(arguments, Target::Decrypt, arguments.get_one::<String>("key").cloned())?
+        .ok_or(Failure::Usage("The key is missing!".to_string()))
+}
+
+fn insecure(arguments: &ArgMatches) -> bool {
+    *arguments.get_one::<bool>("insecureKey").unwrap_or(&false)
+}
+
+/// Prompts for a key without echoing it, reading an armored key up to its END
+/// marker since it spans several lines.
+fn prompt(message: &str) -> Result<String, Failure> {
+    let failed = |error| Failure::Io(format!("Failed to prompt for the key: {}!", error));
+    let mut lines = vec![rpassword::prompt_password(message).map_err(failed)?];
+    if Armor::isArmored(lines[0].as_bytes()) {
+        let end = format!("{}END ", armor::BOUNDARY);
+        loop {
+            match rpassword::read_password() {
+                Ok(line) => {
+                    let ended = line.trim_start().starts_with(&end);
+                    lines.push(line);
+                    if ended {
+                        break;
                     }
-                    Err(error) => format!("{}", error.bright_red())
                 }
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(failed(error)),
             }
         }
-        .bright_blue()
-        .bold()
-        .underline()
-    )
+    }
+    Ok(lines.join("\n"))
+}
+
+fn serialize<T: TryInto<Vec<u8>>>(value: T) -> Result<Vec<u8>, Failure> {
+    value
+        .try_into()
+        .map_err(|_| Failure::Decode("Failed to serialize!".to_string()))
+}
+
+fn open(path: &str) -> Result<File, Failure> {
+    File::open(path).map_err(|error| Failure::Io(format!("Failed to open {}: {}!", path, error)))
+}
+
+/// Reads a whole file, or the standard input for '-'.
+fn read(source: &str) -> Result<Vec<u8>, Failure> {
+    if source == "-" {
+        let mut input = vec![];
+        io::stdin()
+            .lock()
+            .read_to_end(&mut input)
+            .map_err(|error| Failure::Io(format!("Failed to read the standard input: {}!", error)))?;
+        Ok(input)
+    } else {
+        fs::read(source).map_err(|error| Failure::Io(format!("Failed to read {}: {}!", source, error)))
+    }
+}
+
+fn text(path: &str) -> Result<String, Failure> {
+    String::from_utf8(read(path)?)
+        .map_err(|_| Failure::Decode(format!("The {} file is not UTF-8 text!", path)))
+}
+
+fn write(destination: &str, output: &[u8], force: bool) -> Result<(), Failure> {
+    persist(destination, output, force, 0o666)
+}
+
+/// Like `write`, but the file is only readable and writable by its owner.
+fn writeSecret(destination: &str, output: &[u8], force: bool) -> Result<(), Failure> {
+    persist(destination, output, force, 0o600)
+}
+
+/// Writes to the standard output for '-', or to a file through a temporary
+/// sibling renamed over it, so that readers never see a partial file. Without
+/// `force` the temporary file is hard-linked instead, which fails rather than
+/// replacing a file created in the meantime.
+fn persist(destination: &str, output: &[u8], force: bool, mode: u32) -> Result<(), Failure> {
+    if destination == "-" {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(output)
+            .and_then(|_| stdout.flush())
+            .map_err(|error| Failure::Io(format!("Failed to write to the standard output: {}!", error)));
+    }
+    let path = Path::new(destination);
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return Err(Failure::Usage(format!("{} is not a file name!", destination))),
+    };
+    let temporary = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name,
+        hex::encode(rand::random::<[u8; 8]>())
+    ));
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(&temporary)
+        .and_then(|mut file| {
+            file.write_all(output)?;
+            file.sync_all()
+        })
+        .and_then(|_| match force {
+            true => fs::rename(&temporary, path),
+            false => fs::hard_link(&temporary, path).and_then(|_| fs::remove_file(&temporary)),
+        });
+    match written {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            let _ = fs::remove_file(&temporary);
+            Err(Failure::Io(match error.kind() {
+                io::ErrorKind::AlreadyExists => format!(
+                    "Refusing to overwrite the existing {} file (use --force)!",
+                    destination
+                ),
+                _ => format!("Failed to write the {} file: {}!", destination, error),
+            }))
+        }
+    }
+}
+
+fn forced(arguments: &ArgMatches) -> bool {
+    *arguments.get_one::<bool>("force").unwrap_or(&false)
 }
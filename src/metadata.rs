@@ -0,0 +1,86 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ops::timestamp;
+
+use std::fmt;
+
+use flexbuffers;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub content_type: Option<String>,
+    pub created: Option<u64>,
+    pub size: u64,
+}
+
+impl Metadata {
+    pub fn seal(metadata: &Option<Self>) -> Result<Vec<u8>, String> {
+        let encoded = match metadata {
+            Some(metadata) => match flexbuffers::to_vec(metadata) {
+                Ok(encoded) => encoded,
+                Err(_) => return Err("Failed to serialize the metadata!".to_string()),
+            },
+            None => vec![],
+        };
+        Ok([(encoded.len() as u32).to_be_bytes().to_vec(), encoded].concat())
+    }
+
+    pub fn open(section: &[u8]) -> Result<(Option<Self>, Vec<u8>), String> {
+        if section.len() < 4 {
+            return Err("The metadata section is truncated!".to_string());
+        }
+        let length = u32::from_be_bytes(section[..4].try_into().unwrap()) as usize;
+        if section.len() - 4 < length {
+            return Err("The metadata section is truncated!".to_string());
+        }
+        let metadata = if length > 0 {
+            match flexbuffers::from_slice(&section[4..4 + length]) {
+                Ok(metadata) => Some(metadata),
+                Err(_) => return Err("The metadata section is corrupted!".to_string()),
+            }
+        } else {
+            None
+        };
+        Ok((metadata, section[4 + length..].to_vec()))
+    }
+
+    pub fn new(name: Option<String>, content_type: Option<String>) -> Self {
+        Self {
+            name,
+            content_type,
+            created: Some(timestamp()),
+            size: 0,
+        }
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "Name: {}, Content Type: {}, Created: {}, Size: {} bytes",
+            self.name.as_deref().unwrap_or("-"),
+            self.content_type.as_deref().unwrap_or("-"),
+            self.created
+                .map(|created| created.to_string())
+                .unwrap_or("-".to_string()),
+            self.size
+        )
+    }
+}
@@ -16,6 +16,8 @@
 
 use crate::consts;
 
+use std::time::SystemTime;
+
 use blake3;
 use rand::{thread_rng, Rng};
 
@@ -30,7 +32,14 @@ pub fn randomness(size: u16) -> Vec<u8> {
         .collect::<Vec<u8>>()
 }
 
-pub fn keyedHash(input: &Vec<u8>, key: &Vec<u8>, outSize: Option<u16>) -> Vec<u8> {
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn keyedHash(input: &[u8], key: &[u8], outSize: Option<u16>) -> Vec<u8> {
     let keyIpad: Vec<u8> = exclusiveOR(key, IPAD.to_be_bytes().to_vec().as_ref());
     let keyOpad: Vec<u8> = exclusiveOR(key, OPAD.to_be_bytes().to_vec().as_ref());
 
@@ -47,7 +56,7 @@ pub fn keyedHash(input: &Vec<u8>, key: &Vec<u8>, outSize: Option<u16>) -> Vec<u8
         }
     ];
 
-    Hasher.update([keyIpad, input.clone()].concat().as_slice());
+    Hasher.update([keyIpad.as_slice(), input].concat().as_slice());
     Hasher.finalize_xof().fill(&mut Hash);
 
     Hasher.reset();
@@ -58,7 +67,15 @@ pub fn keyedHash(input: &Vec<u8>, key: &Vec<u8>, outSize: Option<u16>) -> Vec<u8
     Hash.to_vec()
 }
 
-pub fn exclusiveOR(fixed: &Vec<u8>, modular: &Vec<u8>) -> Vec<u8> {
+pub fn prehash(domain: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut Hasher = blake3::Hasher::new();
+    for part in parts {
+        Hasher.update(part);
+    }
+    [domain, Hasher.finalize().as_bytes()].concat()
+}
+
+pub fn exclusiveOR(fixed: &[u8], modular: &[u8]) -> Vec<u8> {
     let mut encrypted: Vec<u8> = vec![];
     for offset in 0..fixed.len() {
         encrypted.push(fixed[offset] ^ modular[offset % modular.len()])
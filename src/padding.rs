@@ -0,0 +1,141 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, str};
+
+pub const FRAME_SIZE: usize = 1 + 4 + 8;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Padding {
+    #[default]
+    None,
+    Padme,
+    PowerOfTwo,
+    Bucket(u32),
+}
+
+impl Padding {
+    pub fn size(&self, length: usize) -> Result<usize, String> {
+        Ok(match *self {
+            Padding::None => length,
+            Padding::Padme => {
+                if length < 2 {
+                    length
+                } else {
+                    let exponent = usize::BITS - 1 - length.leading_zeros();
+                    let bits = u32::BITS - exponent.leading_zeros();
+                    let mask = (1usize << (exponent - bits)) - 1;
+                    (length + mask) & !mask
+                }
+            }
+            Padding::PowerOfTwo => length.next_power_of_two(),
+            Padding::Bucket(bucket) => {
+                if bucket == 0 {
+                    return Err("The padding bucket cannot be empty!".to_string());
+                }
+                let bucket = bucket as usize;
+                length.max(1).div_ceil(bucket) * bucket
+            }
+        })
+    }
+
+    pub fn pad(&self, input: &Vec<u8>) -> Result<Vec<u8>, String> {
+        let framed = FRAME_SIZE + input.len();
+        let mut padded = Vec::with_capacity(self.size(framed)?);
+        padded.push((*self).into());
+        padded.extend(
+            match *self {
+                Padding::Bucket(bucket) => bucket,
+                _ => 0,
+            }
+            .to_be_bytes(),
+        );
+        padded.extend((input.len() as u64).to_be_bytes());
+        padded.extend(input);
+        padded.resize(self.size(framed)?, 0);
+        Ok(padded)
+    }
+
+    pub fn unpad(padded: &[u8]) -> Result<(Self, Vec<u8>), String> {
+        if padded.len() < FRAME_SIZE {
+            return Err("The padded payload is truncated!".to_string());
+        }
+        let parameter = u32::from_be_bytes(padded[1..5].try_into().unwrap());
+        let padding = match padded[0] {
+            0 => Padding::None,
+            1 => Padding::Padme,
+            2 => Padding::PowerOfTwo,
+            3 => Padding::Bucket(parameter),
+            byte => {
+                return Err(format!(
+                    "'{}' is not a valid representation of a padding policy!",
+                    byte
+                ))
+            }
+        };
+        let length = u64::from_be_bytes(padded[5..FRAME_SIZE].try_into().unwrap());
+        if length > (padded.len() - FRAME_SIZE) as u64
+            || padding.size(FRAME_SIZE + length as usize)? != padded.len()
+        {
+            return Err("The padding doesn't match its policy!".to_string());
+        }
+        Ok((
+            padding,
+            padded[FRAME_SIZE..FRAME_SIZE + length as usize].to_vec(),
+        ))
+    }
+}
+
+impl From<Padding> for u8 {
+    fn from(padding: Padding) -> Self {
+        match padding {
+            Padding::None => 0,
+            Padding::Padme => 1,
+            Padding::PowerOfTwo => 2,
+            Padding::Bucket(_) => 3,
+        }
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::None => write!(formatter, "none"),
+            Self::Padme => write!(formatter, "padme"),
+            Self::PowerOfTwo => write!(formatter, "power"),
+            Self::Bucket(bucket) => write!(formatter, "bucket:{}", bucket),
+        }
+    }
+}
+
+impl str::FromStr for Padding {
+    type Err = String;
+
+    fn from_str(padding: &str) -> Result<Self, Self::Err> {
+        match padding.to_lowercase().as_str() {
+            "none" => Ok(Padding::None),
+            "padme" => Ok(Padding::Padme),
+            "power" => Ok(Padding::PowerOfTwo),
+            other => match other.strip_prefix("bucket:").map(str::parse::<u32>) {
+                Some(Ok(bucket)) if bucket > 0 => Ok(Padding::Bucket(bucket)),
+                _ => Err(format!(
+                    "Unknown padding '{}', the options are 'none', 'padme', 'power' & 'bucket:<size>'!",
+                    padding
+                )),
+            },
+        }
+    }
+}
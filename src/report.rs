@@ -0,0 +1,163 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use hex;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Header,
+    Mac,
+    Signature,
+    Countersignature,
+    Policy,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub kind: Kind,
+    pub signer: Option<Vec<u8>>,
+    pub scheme: Option<String>,
+    pub timestamp: Option<u64>,
+    pub outcome: Outcome,
+    pub warning: Option<String>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+
+    pub fn signed(
+        kind: Kind,
+        signer: Option<Vec<u8>>,
+        scheme: Option<String>,
+        timestamp: Option<u64>,
+        outcome: Outcome,
+    ) -> Self {
+        Self {
+            kind,
+            signer,
+            scheme,
+            timestamp,
+            outcome,
+            warning: None,
+        }
+    }
+
+    pub fn new(kind: Kind, outcome: Outcome) -> Self {
+        Self::signed(kind, None, None, None, outcome)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.failure().is_none()
+    }
+
+    pub fn failure(&self) -> Option<String> {
+        for check in &self.checks {
+            if let Outcome::Failed(reason) = &check.outcome {
+                return Some(reason.clone());
+            }
+        }
+        if !self
+            .checks
+            .iter()
+            .any(|check| check.passed() && check.kind != Kind::Header)
+        {
+            return Some("Cannot verify integrity as it's not defined!".to_string());
+        }
+        None
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Header => write!(formatter, "header"),
+            Self::Mac => write!(formatter, "keyed hash"),
+            Self::Signature => write!(formatter, "signature"),
+            Self::Countersignature => write!(formatter, "countersignature"),
+            Self::Policy => write!(formatter, "policy"),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Passed => write!(formatter, "passed"),
+            Self::Failed(reason) => write!(formatter, "failed: {}", reason),
+            Self::Skipped(reason) => write!(formatter, "skipped: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let details = [
+            self.scheme.clone(),
+            self.signer
+                .as_ref()
+                .map(|signer| format!("signer {}", hex::encode(signer))),
+            self.timestamp
+                .map(|timestamp| format!("signed at {}", timestamp)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>();
+        if details.is_empty() {
+            write!(formatter, "{}: {}", self.kind, self.outcome)?;
+        } else {
+            write!(
+                formatter,
+                "{} ({}): {}",
+                self.kind,
+                details.join(", "),
+                self.outcome
+            )?;
+        }
+        if let Some(warning) = &self.warning {
+            write!(formatter, " (warning: {})", warning)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let lines = self
+            .checks
+            .iter()
+            .map(|check| check.to_string())
+            .collect::<Vec<String>>();
+        write!(formatter, "{}", lines.join("\n"))
+    }
+}
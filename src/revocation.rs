@@ -0,0 +1,177 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    armor::{self, Armor},
+    consts,
+    keys::Signing,
+    ops::timestamp,
+};
+
+use std::{fmt, str};
+
+use flexbuffers;
+use hex;
+use serde::{Deserialize, Serialize};
+
+pub const DOMAIN: &str = "Komatta 2022 key revocation";
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum Reason {
+    #[default]
+    Unspecified,
+    Compromised,
+    Superseded,
+    Retired,
+}
+
+/// A revocation certificate, self-signed by the revoked key so that anyone
+/// holding its public key can check it without trusting the sender.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revocation {
+    pub version: u8,
+    pub key: Vec<u8>,
+    pub reason: Reason,
+    pub revoked: u64,
+    signature: Vec<u8>,
+}
+
+impl Revocation {
+    pub fn revoke(
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        reason: Reason,
+        revoked: Option<u64>,
+    ) -> Result<Self, String> {
+        let mut revocation = Self {
+            version: consts::REVOCATION_VERSION,
+            key: keys.id()?,
+            reason,
+            revoked: revoked.unwrap_or_else(timestamp),
+            signature: vec![],
+        };
+        revocation.signature = keys.signUnchecked(revocation.digest())?;
+        Ok(revocation)
+    }
+
+    pub fn verify(&self, keys: &Signing::Keys<Option<Vec<u8>>>) -> Result<bool, String> {
+        if self.version != consts::REVOCATION_VERSION {
+            return Err(format!("Unsupported revocation version {}!", self.version));
+        }
+        if self.key != keys.id()? {
+            return Err(format!(
+                "The revocation is for {}, not for {}!",
+                hex::encode(&self.key),
+                hex::encode(keys.id()?)
+            ));
+        }
+        keys.public()?
+            .verifyUnchecked(self.digest(), self.signature.clone())
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        [
+            DOMAIN.as_bytes().to_vec(),
+            [self.version].to_vec(),
+            self.key.clone(),
+            [self.reason.into()].to_vec(),
+            self.revoked.to_be_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    pub fn armor(&self) -> Result<String, String> {
+        let mut armor = Armor::new(
+            armor::Kind::Revocation,
+            match TryInto::<Vec<u8>>::try_into(self.clone()) {
+                Ok(encoded) => encoded,
+                Err(_) => return Err("Failed to serialize the revocation!".to_string()),
+            },
+        );
+        armor
+            .headers
+            .push(("Key-ID".to_string(), hex::encode(&self.key)));
+        armor
+            .headers
+            .push(("Reason".to_string(), self.reason.to_string()));
+        Ok(armor.encode())
+    }
+
+    pub fn dearmor(text: &str) -> Result<Self, String> {
+        let armor = Armor::decode(text)?;
+        if armor.kind != armor::Kind::Revocation {
+            return Err(format!("Expected a revocation but found a {}!", armor.kind));
+        }
+        match Self::try_from(armor.data) {
+            Ok(revocation) => Ok(revocation),
+            Err(_) => Err("The revocation is corrupted!".to_string()),
+        }
+    }
+}
+
+impl From<Reason> for u8 {
+    fn from(reason: Reason) -> Self {
+        match reason {
+            Reason::Unspecified => 0,
+            Reason::Compromised => 1,
+            Reason::Superseded => 2,
+            Reason::Retired => 3,
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Unspecified => write!(formatter, "unspecified"),
+            Self::Compromised => write!(formatter, "compromised"),
+            Self::Superseded => write!(formatter, "superseded"),
+            Self::Retired => write!(formatter, "retired"),
+        }
+    }
+}
+
+impl str::FromStr for Reason {
+    type Err = String;
+
+    fn from_str(reason: &str) -> Result<Self, Self::Err> {
+        match reason {
+            "unspecified" => Ok(Self::Unspecified),
+            "compromised" => Ok(Self::Compromised),
+            "superseded" => Ok(Self::Superseded),
+            "retired" => Ok(Self::Retired),
+            _ => Err(format!(
+                "'{}' is not a revocation reason (unspecified, compromised, superseded or retired)!",
+                reason
+            )),
+        }
+    }
+}
+
+impl TryInto<Vec<u8>> for Revocation {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Revocation {
+    type Error = flexbuffers::DeserializationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        flexbuffers::from_slice(&value)
+    }
+}
@@ -0,0 +1,184 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{consts, ops::randomness};
+
+use blake3;
+use flexbuffers;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    pub index: u8,
+    pub threshold: u8,
+    pub id: Vec<u8>,
+    checksum: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl Share {
+    pub fn verify(&self) -> Result<&Self, String> {
+        if self.index == 0 {
+            return Err("Share index 0 is reserved for the secret!".to_string());
+        }
+        if self.checksum != Self::checksum(self.index, self.threshold, &self.id, &self.value) {
+            return Err(format!("Share {} is corrupted!", self.index));
+        }
+        Ok(self)
+    }
+
+    fn checksum(index: u8, threshold: u8, id: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut Hasher = blake3::Hasher::new();
+        Hasher.update(&[index, threshold]);
+        Hasher.update(id);
+        Hasher.update(value);
+        Hasher.finalize().as_bytes()[..consts::SHARE_CHECKSUM_SIZE.default as usize].to_vec()
+    }
+
+    fn new(index: u8, threshold: u8, id: Vec<u8>, value: Vec<u8>) -> Self {
+        Self {
+            index,
+            threshold,
+            checksum: Self::checksum(index, threshold, &id, &value),
+            id,
+            value,
+        }
+    }
+}
+
+pub fn split(secret: &[u8], id: Vec<u8>, threshold: u8, shares: u8) -> Result<Vec<Share>, String> {
+    consts::SHARES.check(threshold.into())?;
+    consts::SHARES.check(shares.into())?;
+    if threshold > shares {
+        return Err(format!(
+            "The threshold ({}) cannot be greater than the number of shares ({})!",
+            threshold, shares
+        ));
+    }
+
+    let coefficients: Vec<Vec<u8>> = secret
+        .iter()
+        .map(|byte| [vec![*byte], randomness(threshold as u16 - 1)].concat())
+        .collect();
+
+    Ok((1..=shares)
+        .map(|index| {
+            Share::new(
+                index,
+                threshold,
+                id.clone(),
+                coefficients
+                    .iter()
+                    .map(|polynomial| {
+                        polynomial
+                            .iter()
+                            .rev()
+                            .fold(0, |value, coefficient| add(multiply(value, index), *coefficient))
+                    })
+                    .collect(),
+            )
+        })
+        .collect())
+}
+
+pub fn combine(shares: &Vec<Share>) -> Result<Vec<u8>, String> {
+    let first = shares
+        .first()
+        .ok_or("Cannot combine without any share!".to_string())?;
+
+    let mut selected: Vec<&Share> = vec![];
+    for share in shares {
+        share.verify()?;
+        if share.threshold != first.threshold || share.id != first.id {
+            return Err(format!(
+                "Share {} doesn't belong to the same key as share {}!",
+                share.index, first.index
+            ));
+        }
+        if share.value.len() != first.value.len() {
+            return Err(format!("Share {} has an unexpected length!", share.index));
+        }
+        if !selected.iter().any(|chosen| chosen.index == share.index) {
+            selected.push(share);
+        }
+    }
+
+    if selected.len() < first.threshold.into() {
+        return Err(format!(
+            "{} distinct shares are required but only {} were given!",
+            first.threshold,
+            selected.len()
+        ));
+    }
+    selected.truncate(first.threshold.into());
+
+    Ok((0..first.value.len())
+        .map(|offset| {
+            selected.iter().fold(0, |secret, share| {
+                let basis = selected
+                    .iter()
+                    .filter(|other| other.index != share.index)
+                    .fold(1, |basis, other| {
+                        multiply(
+                            basis,
+                            divide(other.index, add(other.index, share.index)),
+                        )
+                    });
+                add(secret, multiply(share.value[offset], basis))
+            })
+        })
+        .collect())
+}
+
+fn add(left: u8, right: u8) -> u8 {
+    left ^ right
+}
+
+fn multiply(mut left: u8, mut right: u8) -> u8 {
+    let mut product = 0;
+    while right != 0 {
+        if right & 1 != 0 {
+            product ^= left;
+        }
+        left = (left << 1) ^ if left & 0x80 != 0 { 0x1b } else { 0 };
+        right >>= 1;
+    }
+    product
+}
+
+fn divide(left: u8, right: u8) -> u8 {
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = multiply(inverse, right);
+    }
+    multiply(left, inverse)
+}
+
+impl TryInto<Vec<u8>> for Share {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Share {
+    type Error = flexbuffers::DeserializationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        flexbuffers::from_slice(&value)
+    }
+}
@@ -0,0 +1,252 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    armor::{self, Armor, BOUNDARY},
+    consts,
+    keys::Signing,
+    ops::timestamp,
+};
+
+use std::io::{self, Read};
+
+use blake3;
+use flexbuffers;
+use hex;
+use serde::{Deserialize, Serialize};
+
+pub const DOMAIN: &str = "Komatta 2022 detached signature";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub version: u8,
+    pub signer: Vec<u8>,
+    #[serde(default = "Signing::Algorithm::legacy")]
+    pub algorithm: Signing::Algorithm,
+    pub created: Option<u64>,
+    signature: Vec<u8>,
+}
+
+impl Signature {
+    pub fn sign(keys: &Signing::Keys<Option<Vec<u8>>>, input: &[u8]) -> Result<Self, String> {
+        Self::signStream(keys, &mut io::Cursor::new(input))
+    }
+
+    pub fn signStream(
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        input: &mut impl Read,
+    ) -> Result<Self, String> {
+        let mut signature = Self {
+            version: consts::SIGNATURE_VERSION,
+            signer: keys.id()?,
+            algorithm: keys.algorithm,
+            created: Some(timestamp()),
+            signature: vec![],
+        };
+        signature.signature = keys.sign(signature.digest(input)?)?;
+        Ok(signature)
+    }
+
+    pub fn verify(
+        &self,
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        input: &[u8],
+    ) -> Result<bool, String> {
+        self.verifyStreamAt(keys, &mut io::Cursor::new(input), timestamp())
+    }
+
+    pub fn verifyAt(
+        &self,
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        input: &[u8],
+        at: u64,
+    ) -> Result<bool, String> {
+        self.verifyStreamAt(keys, &mut io::Cursor::new(input), at)
+    }
+
+    pub fn verifyStream(
+        &self,
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        input: &mut impl Read,
+    ) -> Result<bool, String> {
+        self.verifyStreamAt(keys, input, timestamp())
+    }
+
+    pub fn verifyStreamAt(
+        &self,
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        input: &mut impl Read,
+        at: u64,
+    ) -> Result<bool, String> {
+        if self.version != consts::SIGNATURE_VERSION {
+            return Err(format!("Unsupported signature version {}!", self.version));
+        }
+        if self.signer != keys.id()? {
+            return Err(format!(
+                "The signature was made by {}, not by {}!",
+                hex::encode(&self.signer),
+                hex::encode(keys.id()?)
+            ));
+        }
+        if self.algorithm != keys.algorithm {
+            return Err(format!(
+                "The signature was made with {}, not with {}!",
+                self.algorithm, keys.algorithm
+            ));
+        }
+        keys.public()?
+            .verifyAt(self.digest(input)?, self.signature.clone(), at)
+    }
+
+    fn digest(&self, input: &mut impl Read) -> Result<Vec<u8>, String> {
+        let mut Hasher = blake3::Hasher::new();
+        if io::copy(input, &mut Hasher).is_err() {
+            return Err("Failed to read the input to sign!".to_string());
+        }
+        Ok([
+            DOMAIN.as_bytes().to_vec(),
+            [self.version].to_vec(),
+            self.signer.clone(),
+            match self.created {
+                Some(created) => [[1].to_vec(), created.to_be_bytes().to_vec()].concat(),
+                None => [0].to_vec(),
+            },
+            Hasher.finalize().as_bytes().to_vec(),
+            match self.algorithm {
+                Signing::Algorithm::Dilithium5 => vec![],
+                algorithm => [algorithm.into()].to_vec(),
+            },
+        ]
+        .concat())
+    }
+
+    pub fn armor(&self) -> Result<String, String> {
+        let mut armor = Armor::new(
+            armor::Kind::Signature,
+            match TryInto::<Vec<u8>>::try_into(self.clone()) {
+                Ok(encoded) => encoded,
+                Err(_) => return Err("Failed to serialize the signature!".to_string()),
+            },
+        );
+        armor
+            .headers
+            .push(("Key-ID".to_string(), hex::encode(&self.signer)));
+        Ok(armor.encode())
+    }
+
+    pub fn dearmor(text: &str) -> Result<Self, String> {
+        let armor = Armor::decode(text)?;
+        if armor.kind != armor::Kind::Signature {
+            return Err(format!("Expected a signature but found a {}!", armor.kind));
+        }
+        match Self::try_from(armor.data) {
+            Ok(signature) => Ok(signature),
+            Err(_) => Err("The signature is corrupted!".to_string()),
+        }
+    }
+}
+
+pub fn clearsign(keys: &Signing::Keys<Option<Vec<u8>>>, message: &str) -> Result<String, String> {
+    let message = message.replace("\r\n", "\n");
+    let signature = Signature::sign(keys, message.as_bytes())?;
+    Ok(format!(
+        "{}BEGIN KOMATTA SIGNED MESSAGE{}\n{}\n{}",
+        BOUNDARY,
+        BOUNDARY,
+        message
+            .split('\n')
+            .map(|line| {
+                if line.starts_with('-') {
+                    format!("- {}", line)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        signature.armor()?
+    ))
+}
+
+pub fn clearsigned(text: &str) -> Result<(String, Signature), String> {
+    let text = text.replace("\r\n", "\n");
+    let (message, signature) = text
+        .trim_start()
+        .strip_prefix(&format!(
+            "{}BEGIN KOMATTA SIGNED MESSAGE{}\n",
+            BOUNDARY, BOUNDARY
+        ))
+        .and_then(|text| {
+            text.split_once(&format!(
+                "\n{}BEGIN KOMATTA {}{}",
+                BOUNDARY,
+                armor::Kind::Signature,
+                BOUNDARY
+            ))
+        })
+        .ok_or("The text isn't a clearsigned message!".to_string())?;
+    let mut lines = vec![];
+    for (number, line) in message.split('\n').enumerate() {
+        if let Some(escaped) = line.strip_prefix("- ") {
+            lines.push(escaped);
+        } else if line.starts_with('-') {
+            return Err(format!(
+                "Line {} of the signed message is not dash-escaped!",
+                number + 2
+            ));
+        } else {
+            lines.push(line);
+        }
+    }
+    Ok((
+        lines.join("\n"),
+        Signature::dearmor(&format!(
+            "{}BEGIN KOMATTA {}{}{}",
+            BOUNDARY,
+            armor::Kind::Signature,
+            BOUNDARY,
+            signature
+        ))?,
+    ))
+}
+
+pub fn verifyClearsigned(
+    keys: &Signing::Keys<Option<Vec<u8>>>,
+    text: &str,
+) -> Result<String, String> {
+    let (message, signature) = clearsigned(text)?;
+    if signature.verify(keys, message.as_bytes())? {
+        Ok(message)
+    } else {
+        Err("Signature is invalid!".to_string())
+    }
+}
+
+impl TryInto<Vec<u8>> for Signature {
+    type Error = flexbuffers::SerializationError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        flexbuffers::to_vec(&self)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Signature {
+    type Error = flexbuffers::DeserializationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        flexbuffers::from_slice(&value)
+    }
+}
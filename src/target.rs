@@ -18,8 +18,9 @@ use std::{fmt, str};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
 pub enum Target {
+    #[default]
     Encrypt,
     Decrypt,
 }
@@ -49,9 +50,3 @@ impl str::FromStr for Target {
         }
     }
 }
-
-impl Default for Target {
-    fn default() -> Self {
-        Self::Encrypt
-    }
-}
@@ -0,0 +1,202 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    certificate::Trust,
+    keys::Signing,
+    report::{Check, Kind, Outcome},
+    revocation::Revocation,
+    signature::Signature,
+};
+
+use std::fmt;
+
+use hex;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Policy {
+    #[default]
+    All,
+    Any,
+    Threshold(usize, Vec<Vec<u8>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    pub keyring: Vec<Signing::Keys<Option<Vec<u8>>>>,
+    pub policy: Policy,
+    pub revocations: Vec<Revocation>,
+    pub trust: Option<Trust>,
+}
+
+impl Verifier {
+    pub fn check(
+        &self,
+        construction: &[u8],
+        primary: Option<Vec<u8>>,
+        signatures: &Vec<Signature>,
+        at: u64,
+    ) -> Vec<Check> {
+        let mut valid: Vec<Vec<u8>> = primary.into_iter().collect();
+        let mut checks: Vec<Check> = vec![];
+
+        for signature in signatures {
+            let signer = match self
+                .keyring
+                .iter()
+                .find(|keys| keys.id().ok().as_ref() == Some(&signature.signer))
+            {
+                Some(keys) => Ok(keys.clone()),
+                None => match &self.trust {
+                    Some(trust) => trust.resolve(&signature.signer, at, &self.revocations),
+                    None => Err("unknown signer".to_string()),
+                },
+            };
+            let mut warning = None;
+            let (scheme, outcome) = match signer {
+                Ok(keys) => (
+                    Some(keys.scheme()),
+                    match signature
+                        .verifyAt(&keys, construction, at)
+                        .and_then(|verified| {
+                            Ok((verified, self.revoked(&keys, signature.created)?))
+                        }) {
+                        Ok((true, revoked)) => {
+                            warning = revoked;
+                            valid.push(signature.signer.clone());
+                            Outcome::Passed
+                        }
+                        Ok((false, _)) => Outcome::Failed("Signature is invalid!".to_string()),
+                        Err(error) => Outcome::Failed(error),
+                    },
+                ),
+                Err(error) => (None, Outcome::Skipped(error)),
+            };
+            let mut check = Check::signed(
+                Kind::Countersignature,
+                Some(signature.signer.clone()),
+                scheme,
+                signature.created,
+                outcome,
+            );
+            check.warning = warning;
+            checks.push(check);
+        }
+        valid.sort();
+        valid.dedup();
+
+        // Every key of the keyring must have signed, so stripping a countersignature fails.
+        if self.policy == Policy::All {
+            for keys in &self.keyring {
+                match keys.id() {
+                    Ok(id) if valid.contains(&id) => {}
+                    Ok(id) => checks.push(Check::signed(
+                        Kind::Countersignature,
+                        Some(id),
+                        Some(keys.scheme()),
+                        None,
+                        Outcome::Failed("The keyring key didn't sign!".to_string()),
+                    )),
+                    Err(error) => checks.push(Check::new(
+                        Kind::Countersignature,
+                        Outcome::Failed(error),
+                    )),
+                }
+            }
+        }
+
+        let rejected = checks.iter().filter(|check| !check.passed()).count();
+        let satisfied = match &self.policy {
+            Policy::All => rejected == 0 && !valid.is_empty(),
+            Policy::Any => !valid.is_empty(),
+            Policy::Threshold(threshold, signers) => {
+                valid
+                    .iter()
+                    .filter(|signer| signers.contains(*signer))
+                    .count()
+                    >= *threshold
+            }
+        };
+
+        checks.push(Check::new(
+            Kind::Policy,
+            if satisfied {
+                Outcome::Passed
+            } else {
+                Outcome::Failed(format!(
+                    "The signatures don't satisfy the '{}' policy ({} valid, {} rejected)!",
+                    self.policy,
+                    valid.len(),
+                    rejected
+                ))
+            },
+        ));
+        checks
+    }
+
+    pub fn revoked(
+        &self,
+        keys: &Signing::Keys<Option<Vec<u8>>>,
+        created: Option<u64>,
+    ) -> Result<Option<String>, String> {
+        let id = keys.id()?;
+        let revocation = self
+            .revocations
+            .iter()
+            .find(|revocation| revocation.key == id && revocation.verify(keys).unwrap_or(false));
+        match (revocation, created) {
+            (None, _) => Ok(None),
+            (Some(revocation), Some(created)) if created < revocation.revoked => Ok(Some(format!(
+                "the key was revoked at {} ({}) after signing",
+                revocation.revoked, revocation.reason
+            ))),
+            (Some(revocation), _) => Err(format!(
+                "The key {} was revoked at {} ({})!",
+                hex::encode(&id),
+                revocation.revoked,
+                revocation.reason
+            )),
+        }
+    }
+
+    pub fn new(keyring: Vec<Signing::Keys<Option<Vec<u8>>>>, policy: Policy) -> Self {
+        Self {
+            keyring,
+            policy,
+            revocations: vec![],
+            trust: None,
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::All => write!(formatter, "all"),
+            Self::Any => write!(formatter, "any"),
+            Self::Threshold(threshold, signers) => write!(
+                formatter,
+                "{} of {}",
+                threshold,
+                signers
+                    .iter()
+                    .map(hex::encode)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
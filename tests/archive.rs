@@ -0,0 +1,73 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{archive, ops::randomness};
+
+use std::{
+    env, fs,
+    os::unix::fs::{symlink, PermissionsExt},
+};
+
+#[test]
+fn archive() -> Result<(), String> {
+    let root = env::temp_dir().join(format!("komatta-{}", hex::encode(randomness(8))));
+    let (source, destination) = (root.join("source"), root.join("destination"));
+    let failed = |error: std::io::Error| error.to_string();
+    fs::create_dir_all(source.join("sub")).map_err(failed)?;
+    fs::write(source.join("sub/secret.conf"), "secret").map_err(failed)?;
+    fs::set_permissions(source.join("sub/secret.conf"), fs::Permissions::from_mode(0o640))
+        .map_err(failed)?;
+
+    let packed = archive::pack(&source)?;
+    let entries = archive::unpack(&packed, &destination, false)?;
+    if entries.iter().map(|entry| entry.path.as_str()).collect::<Vec<&str>>()
+        != ["sub", "sub/secret.conf"]
+    {
+        return Err(format!("Unexpected entries: {:?}", entries));
+    }
+    let unpacked = destination.join("sub/secret.conf");
+    if fs::read_to_string(&unpacked).map_err(failed)? != "secret"
+        || fs::metadata(&unpacked).map_err(failed)?.permissions().mode() & 0o777 != 0o640
+    {
+        return Err("The file wasn't restored with its permissions!".to_string());
+    }
+    if archive::unpack(&packed, &destination, false).is_ok() {
+        return Err("An existing file was overwritten!".to_string());
+    }
+
+    symlink("/etc/passwd", source.join("sub/link")).map_err(failed)?;
+    if archive::pack(&source).is_ok() {
+        return Err("A symbolic link was followed out of the directory!".to_string());
+    }
+
+    // Builders refuse to write such paths, so the name is patched in the header.
+    let mut header = tar::Header::new_old();
+    header.as_old_mut().name[..8].copy_from_slice(b"../evil\0");
+    header.set_size(0);
+    header.set_cksum();
+    let mut builder = tar::Builder::new(vec![]);
+    builder.append(&header, &[][..]).map_err(failed)?;
+    let evil = builder.into_inner().map_err(failed)?;
+    let result = archive::unpack(&evil, &destination, true);
+    fs::remove_dir_all(&root).map_err(failed)?;
+    match result {
+        Err(error) if error.contains("escapes the destination") => Ok(()),
+        Err(error) => Err(format!("The wrong error was reported: {}", error)),
+        Ok(_) => Err("A path traversal entry was extracted!".to_string()),
+    }
+}
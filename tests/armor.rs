@@ -0,0 +1,39 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{
+    armor::{Armor, Kind},
+    ops::randomness,
+};
+
+#[test]
+fn armor() -> Result<(), String> {
+    let armored = Armor::new(Kind::Message, randomness(256)).encode();
+
+    if Armor::decode(&armored)?.data != Armor::decode(&armored.replace("\n", "\r\n"))?.data {
+        return Err("Line endings changed the decoded bytes!".to_string());
+    }
+
+    let mut lines: Vec<String> = armored.lines().map(str::to_string).collect();
+    lines[4] = lines[4].replacen(|_: char| true, "_", 1);
+    match Armor::decode(&lines.join("\n")) {
+        Err(error) if error.starts_with("Line 5 ") => Ok(()),
+        Err(error) => Err(format!("Corruption reported at the wrong place: {}", error)),
+        Ok(_) => Err("Corrupted armor was decoded!".to_string()),
+    }
+}
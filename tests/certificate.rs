@@ -0,0 +1,110 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{
+    certificate::{Certificate, Trust},
+    keys::{
+        Keys,
+        Signing::{self, Algorithm, Usage},
+    },
+    ops::{randomness, timestamp},
+    revocation::{Reason, Revocation},
+    target::Target,
+    verify::{Policy, Verifier},
+    Crypt, Integrity,
+};
+
+#[test]
+fn chain() -> Result<(), String> {
+    let root = Signing::Keys::generate(Algorithm::MlDsa87, None, vec![Usage::Certify]);
+    let team = Signing::Keys::generate(Algorithm::MlDsa87, None, vec![Usage::Sign, Usage::Certify]);
+    let engineer = Signing::Keys::generate(Algorithm::MlDsa65, None, vec![Usage::Sign]);
+
+    let certificates = vec![
+        Certificate::dearmor(
+            &Certificate::certify(&root, &team, "team".to_string(), None)?.armor()?,
+        )?,
+        Certificate::certify(&team, &engineer, "engineer".to_string(), None)?,
+    ];
+    if Certificate::certify(&engineer, &team, "team".to_string(), None).is_ok() {
+        return Err("A signing-only key certified another key!".to_string());
+    }
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, false)?,
+        None,
+        None,
+        randomness(128),
+        Integrity::Unsigned(None),
+    );
+    crypt.input = crypt.process()?;
+    crypt.countersign(&engineer)?;
+    crypt.target = Target::Decrypt;
+
+    let mut verifier = Verifier::new(vec![], Policy::All);
+    verifier.trust = Some(Trust::new(vec![root.public()?], certificates.clone()));
+    crypt.verifier = Some(verifier.clone());
+    crypt.clone().process()?;
+
+    verifier.trust = Some(Trust::new(vec![root.public()?], certificates[1..].to_vec()));
+    crypt.verifier = Some(verifier.clone());
+    if crypt.clone().process().is_ok() {
+        return Err("A key without a chain to the root was accepted!".to_string());
+    }
+
+    // Revoking a link in the middle breaks the chain of the keys below it.
+    verifier.trust = Some(Trust::new(vec![root.public()?], certificates.clone()));
+    verifier.revocations = vec![Revocation::revoke(&team, Reason::Compromised, None)?];
+    crypt.verifier = Some(verifier);
+    if crypt.process().is_ok() {
+        return Err("A chain through a revoked key was accepted!".to_string());
+    }
+
+    let mut stripped = certificates[1].clone();
+    stripped.subject.validity = None;
+    if stripped.verify(&team.public()?, timestamp()).unwrap_or(false) {
+        return Err("The validity of the subject isn't certified!".to_string());
+    }
+
+    // The primary signer needs a chain to the roots as well.
+    let master = Keys::new(None, None, true)?;
+    let mut signed = Crypt::new(
+        Target::Encrypt,
+        master.clone(),
+        None,
+        None,
+        randomness(128),
+        Integrity::Signed(None),
+    );
+    signed.input = signed.process()?;
+    signed.target = Target::Decrypt;
+    signed.keys = signed.keys.public()?;
+    let mut verifier = Verifier::new(vec![], Policy::All);
+    verifier.trust = Some(Trust::new(vec![root.public()?], certificates.clone()));
+    signed.verifier = Some(verifier.clone());
+    if signed.clone().process().is_ok() {
+        return Err("A signer without a chain to the root was accepted!".to_string());
+    }
+    let mut chained = certificates;
+    chained.push(Certificate::certify(&team, &master.signing()?, "master".to_string(), None)?);
+    verifier.trust = Some(Trust::new(vec![root.public()?], chained));
+    signed.verifier = Some(verifier);
+    signed.process()?;
+    Ok(())
+}
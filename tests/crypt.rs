@@ -16,7 +16,18 @@
 
 #![allow(non_snake_case)]
 
-use Komatta::{keys::Keys, ops::randomness, target::Target, Crypt, Integrity};
+use Komatta::{
+    compression::Compression,
+    consts::FORMAT_VERSION,
+    keys::{Cost, Kem, Keys, Signing},
+    metadata::Metadata,
+    ops::randomness,
+    padding::Padding,
+    report::{Kind, Outcome},
+    target::Target,
+    verify::{Policy, Verifier},
+    Crypt, Hashing, Integrity, Mode,
+};
 
 #[test]
 fn crypt() -> Result<(), String> {
@@ -35,12 +46,431 @@ fn crypt() -> Result<(), String> {
 
     crypt.target = Target::Decrypt;
 
-    crypt.keys = Keys::from(crypt.keys.public()?);
+    if crypt.clone().process()? != input {
+        return Err("The master key didn't decrypt its own crypt!".to_string());
+    }
+
+    crypt.keys = crypt.keys.public()?;
+
+    let decrypted = crypt.clone().process()?;
 
-    let decrypted = crypt.process()?;
+    // The report names the key that signed, not the one given.
+    let mut other = crypt.clone();
+    other.keys = Keys::new(None, None, true)?.public()?;
+    let signer = other
+        .verify()
+        .checks
+        .into_iter()
+        .find(|check| check.kind == Kind::Signature)
+        .and_then(|check| check.signer);
+    if signer.as_deref() != crypt.signer() || signer != Some(crypt.keys.signing()?.id()?) {
+        return Err("The report didn't name the primary signer!".to_string());
+    }
 
     match input == decrypted {
         true => Ok(()),
         false => Err("Input and decrypted bytes are not equal!".to_string()),
     }
 }
+
+#[test]
+fn synthetic() -> Result<(), String> {
+    let input = randomness(128);
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, false)?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Unsigned(None),
+    );
+    crypt.mode = Mode::Synthetic;
+    crypt.associated = b"record".to_vec();
+
+    let (mut first, mut second) = (crypt.clone(), crypt.clone());
+    first.input = first.process()?;
+    second.input = second.process()?;
+    if first.input != second.input {
+        return Err("Synthetic encryption is not deterministic!".to_string());
+    }
+
+    first.target = Target::Decrypt;
+    if first.clone().process()? != input {
+        return Err("Input and decrypted bytes are not equal!".to_string());
+    }
+
+    first.associated = b"another record".to_vec();
+    if first.process().is_ok() {
+        return Err("Decrypted with the wrong associated data!".to_string());
+    }
+
+    // The IV covers everything that is encrypted, not just the input.
+    let (mut named, mut compressed) = (crypt.clone(), crypt.clone());
+    named.attach(Metadata::new(Some("input.bin".to_string()), None));
+    compressed.compression = Compression::Zstd;
+    compressed.level = Some(1);
+    let mut recompressed = compressed.clone();
+    recompressed.level = Some(19);
+    for crypt in [&mut named, &mut compressed, &mut recompressed] {
+        crypt.input = crypt.process()?;
+    }
+    if [named.iv(), compressed.iv(), recompressed.iv()].contains(&second.iv())
+        || compressed.iv() == recompressed.iv()
+    {
+        return Err("The synthetic IV didn't change along the payload!".to_string());
+    }
+    Ok(())
+}
+
+#[test]
+fn import() -> Result<(), String> {
+    let input = randomness(128);
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, false)?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Unsigned(None),
+    );
+    crypt.input = crypt.process()?;
+
+    let imported = |version| {
+        Crypt::import(
+            Target::Decrypt,
+            crypt.keys.clone(),
+            version,
+            crypt.iv().to_vec(),
+            Some(crypt.blockSize()),
+            crypt.input.clone(),
+            crypt.integrity.clone(),
+        )
+    };
+    if imported(0).process().is_ok() {
+        return Err("The crypt was imported as a legacy one!".to_string());
+    }
+    match imported(FORMAT_VERSION).process()? == input {
+        true => Ok(()),
+        false => Err("Input and decrypted bytes are not equal!".to_string()),
+    }
+}
+
+#[test]
+fn compression() -> Result<(), String> {
+    let input = [b"Komatta".repeat(64), randomness(64)].concat();
+
+    for compression in [Compression::Brotli, Compression::Zstd] {
+        let mut crypt = Crypt::new(
+            Target::Encrypt,
+            Keys::new(None, None, false)?,
+            None,
+            None,
+            input.clone(),
+            Integrity::Unsigned(None),
+        );
+        crypt.compression = compression;
+
+        crypt.input = crypt.process()?;
+        if crypt.input.len() >= input.len() {
+            return Err(format!("{} didn't compress the input!", compression));
+        }
+
+        crypt.target = Target::Decrypt;
+        if crypt.process()? != input {
+            return Err("Input and decrypted bytes are not equal!".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn padding() -> Result<(), String> {
+    let keys = Keys::new(None, None, false)?;
+    let mut lengths = vec![];
+
+    for input in [randomness(100), randomness(900)] {
+        let mut crypt = Crypt::new(
+            Target::Encrypt,
+            keys.clone(),
+            None,
+            None,
+            input.clone(),
+            Integrity::Unsigned(None),
+        );
+        crypt.padding = Padding::Bucket(1024);
+        crypt.attach(Metadata::new(Some("input.bin".to_string()), None));
+
+        crypt.input = crypt.process()?;
+        lengths.push(crypt.input.len());
+
+        crypt.target = Target::Decrypt;
+        crypt.padding = Padding::None;
+        if crypt.process()? != input || crypt.padding != Padding::Bucket(1024) {
+            return Err("Input and decrypted bytes are not equal!".to_string());
+        }
+        if crypt.metadata().map(|metadata| metadata.size) != Some(input.len() as u64) {
+            return Err("The metadata wasn't restored!".to_string());
+        }
+    }
+
+    match lengths[0] == lengths[1] {
+        true => Ok(()),
+        false => Err("Padded lengths are not equal!".to_string()),
+    }
+}
+
+#[test]
+fn both() -> Result<(), String> {
+    let input = randomness(128);
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, true)?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Both(None, None),
+    );
+
+    crypt.input = crypt.process()?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = crypt.keys.public()?;
+
+    if crypt.clone().process()? != input {
+        return Err("Input and decrypted bytes are not equal!".to_string());
+    }
+
+    if let Integrity::Both(_, Some(signature)) = &mut crypt.integrity {
+        signature[0] ^= 1;
+    }
+    match crypt.process() {
+        Ok(_) => Err("Decrypted with a tampered signature!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[test]
+fn prehashed() -> Result<(), String> {
+    let input = randomness(128);
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, true)?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Signed(None),
+    );
+    crypt.hashing = Hashing::Prehashed;
+
+    crypt.input = crypt.process()?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = crypt.keys.public()?;
+
+    if crypt.clone().process()? != input {
+        return Err("Input and decrypted bytes are not equal!".to_string());
+    }
+
+    crypt.hashing = Hashing::Direct;
+    match crypt.process() {
+        Ok(_) => Err("A prehashed signature verified as a direct one!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[test]
+fn encapsulated() -> Result<(), String> {
+    let input = randomness(128);
+    let recipient = Kem::Keys::new(Kem::Algorithm::default());
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, true)?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Both(None, None),
+    );
+    crypt.recipient = Some(recipient.public());
+
+    crypt.input = crypt.process()?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = crypt.keys.public()?;
+
+    if crypt.clone().process().is_ok() {
+        return Err("Decrypted without the recipient's secret key!".to_string());
+    }
+
+    crypt.recipient = Some(recipient);
+    match crypt.process()? == input {
+        true => Ok(()),
+        false => Err("Input and decrypted bytes are not equal!".to_string()),
+    }
+}
+
+#[test]
+fn countersigned() -> Result<(), String> {
+    let (first, second) = (Signing::Keys::new(), Signing::Keys::new());
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, true)?,
+        None,
+        None,
+        randomness(128),
+        Integrity::Signed(None),
+    );
+
+    crypt.input = crypt.process()?;
+    crypt.countersign(&first)?;
+    crypt.countersign(&second)?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = crypt.keys.public()?;
+
+    let signers = vec![first.id()?, second.id()?];
+    crypt.verifier = Some(Verifier::new(
+        vec![first.public()?, second.public()?],
+        Policy::Threshold(2, signers.clone()),
+    ));
+    crypt.clone().process()?;
+
+    crypt.verifier = Some(Verifier::new(
+        vec![first.public()?],
+        Policy::Threshold(2, signers),
+    ));
+    if crypt.clone().process().is_ok() {
+        return Err("The policy was satisfied with a single signer!".to_string());
+    }
+
+    crypt.verifier = Some(Verifier::new(
+        vec![first.public()?, second.public()?],
+        Policy::All,
+    ));
+    crypt.clone().process()?;
+
+    // A keyring key that never signed fails 'all', as does a stripped countersignature.
+    let absent = Signing::Keys::new();
+    crypt.verifier = Some(Verifier::new(
+        vec![first.public()?, absent.public()?],
+        Policy::All,
+    ));
+    let missing = crypt.verify().checks.into_iter().find(|check| {
+        check.signer == Some(absent.id().unwrap_or_default())
+            && matches!(check.outcome, Outcome::Failed(_))
+    });
+    if missing.is_none() || crypt.clone().process().is_ok() {
+        return Err("The 'all' policy passed without every keyring signature!".to_string());
+    }
+    crypt.signatures.pop();
+    crypt.verifier = Some(Verifier::new(
+        vec![first.public()?, second.public()?],
+        Policy::All,
+    ));
+    match crypt.process() {
+        Ok(_) => Err("The 'all' policy passed without a stripped countersignature!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[test]
+fn report() -> Result<(), String> {
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::new(None, None, true)?,
+        None,
+        None,
+        randomness(128),
+        Integrity::Both(None, None),
+    );
+
+    crypt.input = crypt.process()?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = crypt.keys.public()?;
+
+    if !crypt.verify().passed() {
+        return Err("The untampered crypt didn't verify!".to_string());
+    }
+
+    if let Integrity::Both(Some(tag), _) = &mut crypt.integrity {
+        tag[0] ^= 1;
+    }
+    let report = crypt.verify();
+    let outcomes = report
+        .checks
+        .iter()
+        .map(|check| (check.kind, check.outcome.clone()))
+        .collect::<Vec<(Kind, Outcome)>>();
+    match outcomes.as_slice() {
+        [(Kind::Header, Outcome::Passed), (Kind::Mac, Outcome::Failed(_)), (Kind::Signature, Outcome::Skipped(_))] => {
+            Ok(())
+        }
+        _ => Err(format!("Unexpected report:\n{}", report)),
+    }
+}
+
+#[test]
+fn cost() -> Result<(), String> {
+    let input = randomness(128);
+    let cost = Cost {
+        memory: 8 * 1024,
+        iterations: 3,
+        parallelism: 2,
+    };
+
+    let mut crypt = Crypt::new(
+        Target::Encrypt,
+        Keys::generate(
+            None,
+            Some(32),
+            Some(Signing::Algorithm::MlDsa44),
+            None,
+            vec![Signing::Usage::Sign],
+            cost,
+        )?,
+        None,
+        None,
+        input.clone(),
+        Integrity::Both(None, None),
+    );
+
+    crypt.input = crypt.process()?;
+
+    crypt.target = Target::Decrypt;
+
+    crypt.keys = Keys::try_from(
+        TryInto::<Vec<u8>>::try_into(crypt.keys.public()?)
+            .map_err(|_| "Failed to serialize the keys!".to_string())?,
+    )
+    .map_err(|_| "Failed to deserialize the keys!".to_string())?;
+    if crypt.keys.cost() != cost {
+        return Err("The Argon2 parameters weren't kept along the keys!".to_string());
+    }
+
+    if crypt.process()? != input {
+        return Err("Input and decrypted bytes are not equal!".to_string());
+    }
+
+    let invalid = Cost {
+        parallelism: 0,
+        ..cost
+    };
+    if Keys::generate(None, None, None, None, vec![], invalid).is_ok() {
+        return Err("Keys with invalid Argon2 parameters were generated!".to_string());
+    }
+    match Keys::new(Some(4), None, false) {
+        Ok(_) => Err("Keys with a salt too short for Argon2 were generated!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
@@ -0,0 +1,46 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{
+    encoding::{Encoding, Transform},
+    ops::randomness,
+};
+
+#[test]
+fn encoding() -> Result<(), String> {
+    let value = randomness(257);
+    for encoding in Encoding::DETECTION {
+        let encoded = Transform::new(value.clone(), encoding, true).encode()?;
+        // Decoding detects the encoding even when told the wrong one.
+        if Transform::new(encoded, Encoding::Base64, true).decode()? != value {
+            return Err(format!("The {} roundtrip changed the value!", encoding));
+        }
+    }
+
+    if Encoding::Base32.decode(b"0o1I-Lab0")? != Encoding::Base32.decode(b"00111AB0")? {
+        return Err("Crockford Base32 isn't forgiving!".to_string());
+    }
+    if Encoding::Base64Url.encode(&[0xfb, 0xff]) != "-_8" {
+        return Err("URL-safe Base64 is padded or not URL-safe!".to_string());
+    }
+    match Transform::new(b"not ~ encoded".to_vec(), Encoding::Hex, false).decode() {
+        Err(error) if error == "Not valid Base16!" => Ok(()),
+        Err(error) => Err(format!("The wrong error was reported: {}", error)),
+        Ok(_) => Err("Garbage was decoded!".to_string()),
+    }
+}
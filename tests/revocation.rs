@@ -0,0 +1,62 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{
+    keys::Signing,
+    ops::{randomness, timestamp},
+    report::Kind,
+    revocation::{Reason, Revocation},
+    signature::Signature,
+    verify::{Policy, Verifier},
+};
+
+#[test]
+fn revocation() -> Result<(), String> {
+    let keys = Signing::Keys::new();
+    let input = randomness(128);
+    let signature = Signature::sign(&keys, &input)?;
+
+    let revocation = Revocation::dearmor(
+        &Revocation::revoke(&keys, Reason::Compromised, Some(timestamp() + 60))?.armor()?,
+    )?;
+    if !revocation.verify(&keys.public()?)? {
+        return Err("The revocation isn't valid for its key!".to_string());
+    }
+
+    let mut verifier = Verifier::new(vec![keys.public()?], Policy::All);
+    verifier.revocations = vec![revocation];
+    let checks = verifier.check(&input, None, &vec![signature.clone()], timestamp());
+    match checks.first() {
+        Some(check) if check.passed() && check.warning.is_some() => (),
+        _ => return Err("A signature made before the revocation wasn't flagged!".to_string()),
+    }
+
+    verifier.revocations = vec![Revocation::revoke(
+        &keys,
+        Reason::Compromised,
+        signature.created.map(|created| created - 1),
+    )?];
+    let checks = verifier.check(&input, None, &vec![signature], timestamp());
+    match checks
+        .iter()
+        .find(|check| check.kind == Kind::Countersignature)
+    {
+        Some(check) if !check.passed() => Ok(()),
+        _ => Err("A signature made after the revocation was accepted!".to_string()),
+    }
+}
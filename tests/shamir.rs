@@ -0,0 +1,39 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::keys::Keys;
+
+#[test]
+fn shamir() -> Result<(), String> {
+    let keys = Keys::new(None, None, true)?;
+    let shares = keys.split(3, 5)?;
+
+    let combined = Keys::combine(&vec![
+        shares[4].clone(),
+        shares[0].clone(),
+        shares[2].clone(),
+    ])?;
+    if TryInto::<Vec<u8>>::try_into(combined).ok() != TryInto::<Vec<u8>>::try_into(keys).ok() {
+        return Err("Combined keys are not equal to the original ones!".to_string());
+    }
+
+    match Keys::combine(&shares[..2].to_vec()) {
+        Ok(_) => Err("Keys were rebuilt from fewer shares than the threshold!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
@@ -0,0 +1,141 @@
+// Komatta
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![allow(non_snake_case)]
+
+use Komatta::{
+    keys::{
+        Cost, Keys,
+        Signing::{self, Algorithm, Usage},
+    },
+    ops::{randomness, timestamp},
+    signature::{clearsign, verifyClearsigned, Signature},
+};
+
+#[test]
+fn detached() -> Result<(), String> {
+    let keys = Signing::Keys::new();
+    let input = randomness(4096);
+
+    let signature = Signature::dearmor(&Signature::sign(&keys, &input)?.armor()?)?;
+    if !signature.verify(&keys.public()?, &input)? {
+        return Err("A signature wasn't valid for its input!".to_string());
+    }
+
+    let export = |keys: Signing::Keys<Option<Vec<u8>>>| {
+        TryInto::<Vec<u8>>::try_into(keys).map_err(|_| "Failed to export the keys!".to_string())
+    };
+    if !signature.verify(&Signing::Keys::verifying(export(keys.public()?)?)?, &input)? {
+        return Err("The exported public key didn't verify the signature!".to_string());
+    }
+    if Signing::Keys::verifying(export(keys.clone())?).is_ok() {
+        return Err("Secret keys were read as a public key!".to_string());
+    }
+
+    match signature.verify(&keys.public()?, &randomness(4096)) {
+        Ok(true) => Err("A signature was valid for another input!".to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn clearsigned() -> Result<(), String> {
+    let keys = Signing::Keys::new();
+    let message = "Release 1.0\n-----\n- notes\n";
+
+    let signed = clearsign(&keys, message)?;
+    if verifyClearsigned(&keys.public()?, &signed)? != message {
+        return Err("The clearsigned message changed!".to_string());
+    }
+
+    match verifyClearsigned(&keys.public()?, &signed.replace("1.0", "2.0")) {
+        Ok(_) => Err("A tampered clearsigned message was valid!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[test]
+fn validity() -> Result<(), String> {
+    let input = randomness(128);
+
+    let certifying = Signing::Keys::generate(Algorithm::default(), None, vec![Usage::Certify]);
+    if Signature::sign(&certifying, &input).is_ok() {
+        return Err("A certification-only key signed a message!".to_string());
+    }
+
+    let now = timestamp();
+    let master = Keys::new(None, None, true)?.signing()?;
+    let mut legacy = master.clone();
+    legacy.validity = None;
+    if master.permits(Usage::Certify, now).is_ok()
+        || legacy.permits(Usage::Certify, now).is_ok()
+        || legacy.permits(Usage::Sign, now).is_err()
+    {
+        return Err("Keys may certify without being generated for it!".to_string());
+    }
+    let expiring = Keys::generate(
+        None,
+        None,
+        Some(Algorithm::default()),
+        Some(now + 60),
+        vec![Usage::Sign, Usage::Certify],
+        Cost::default(),
+    )?
+    .signing()?;
+    if expiring.permits(Usage::Certify, now).is_err() || expiring.permits(Usage::Sign, now + 120).is_ok() {
+        return Err("The validity of the master key wasn't kept!".to_string());
+    }
+
+    let keys = Signing::Keys::generate(Algorithm::default(), Some(now + 60), vec![Usage::Sign]);
+    let signature = Signature::sign(&keys, &input)?;
+    if !signature.verify(&keys.public()?, &input)? {
+        return Err("A signature wasn't valid for its input!".to_string());
+    }
+    if signature
+        .verifyAt(&keys.public()?, &input, now + 120)
+        .is_ok()
+    {
+        return Err("An expired key verified a signature!".to_string());
+    }
+
+    let mut tampered = keys.public()?;
+    if let Some(validity) = tampered.validity.as_mut() {
+        validity.expires = None;
+    }
+    match signature.verifyAt(&tampered, &input, now + 120) {
+        Ok(_) => Err("A key with a tampered validity verified a signature!".to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[test]
+fn algorithms() -> Result<(), String> {
+    let input = randomness(128);
+
+    for algorithm in [
+        Algorithm::Dilithium5,
+        Algorithm::MlDsa44,
+        Algorithm::MlDsa65,
+        Algorithm::MlDsa87,
+    ] {
+        let keys = Signing::Keys::generate(algorithm, None, vec![Usage::Sign]);
+        let signature = Signature::sign(&keys, &input)?;
+        if signature.algorithm != algorithm || !signature.verify(&keys.public()?, &input)? {
+            return Err(format!("A {} signature wasn't valid!", algorithm));
+        }
+    }
+    Ok(())
+}